@@ -0,0 +1,43 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::error::{Result, TranscribeError};
+use crate::handlers::AppState;
+
+/// Routes reachable without an API key or rate limit, so probes keep working.
+/// `/health?deep=true` runs inference, so it checks the admin token itself.
+pub const PUBLIC_PATHS: &[&str] = &["/health", "/live", "/ready"];
+
+/// Require a matching `X-API-Key` header on every non-public route when an
+/// API key is configured
+pub async fn require_api_key(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    if let Some(expected) = state.api_key.as_deref() {
+        if !PUBLIC_PATHS.contains(&request.uri().path()) {
+            let provided = request
+                .headers()
+                .get("x-api-key")
+                .and_then(|v| v.to_str().ok());
+
+            if !provided.is_some_and(|key| constant_time_eq(key.as_bytes(), expected.as_bytes())) {
+                return Err(TranscribeError::Unauthorized(
+                    "Missing or invalid API key".to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(next.run(request).await)
+}
+
+/// Compare without short-circuiting so timing doesn't reveal the matching prefix
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
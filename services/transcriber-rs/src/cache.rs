@@ -0,0 +1,49 @@
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+use crate::error::Result;
+use crate::transcriber::TranscribeOptions;
+
+/// SHA-256 of a file's contents, hex-encoded. Blocking; call from a blocking task.
+pub fn content_hash(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// SHA-256 of in-memory audio, hex-encoded like `content_hash`
+pub fn bytes_hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// Queue key (without the namespace prefix) for a cached result. `identity`
+/// is the transcriber's `cache_identity`, so results from another model or
+/// decoding setup are never served.
+pub fn cache_key(hash: &str, identity: &str, options: &TranscribeOptions) -> String {
+    format!(
+        "cache:{}:{}{}",
+        &bytes_hash(identity.as_bytes())[..16],
+        hash,
+        options_key(options)
+    )
+}
+
+/// Key suffix for the options that change the segments; the language hint
+/// is only reported back, so it is not part of it.
+pub fn options_key(options: &TranscribeOptions) -> String {
+    let mut key = String::new();
+    if options.diarize {
+        key.push_str(":diarized");
+    }
+    if let Some(clip) = options.clip {
+        match clip.end {
+            Some(end) => key.push_str(&format!(":clip={}-{}", clip.start, end)),
+            None => key.push_str(&format!(":clip={}-", clip.start)),
+        }
+    }
+    if let Some(profile) = options.profile {
+        key.push_str(&format!(":profile={}", profile.as_str()));
+    }
+    key
+}
@@ -0,0 +1,197 @@
+use futures::future::join_all;
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde::Serialize;
+use sha2::Sha256;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::config::Config;
+use crate::queue::Queue;
+
+/// Header carrying the `sha256=<hex>` HMAC of the request body
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+
+/// How long one delivery attempt may take
+const ATTEMPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long a claimed callback is reserved for the pod attempting it
+const CLAIM_LEASE: Duration = Duration::from_secs(120);
+
+/// Callbacks attempted concurrently by one pod
+const CLAIM_LIMIT: usize = 16;
+
+/// How often to look for due callbacks when there were none
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Delivers completion callbacks, signing each body. Deliveries are tracked
+/// in Redis and retried with exponential backoff by a background worker, so
+/// their outcome can be inspected and a restart doesn't lose them.
+#[derive(Clone)]
+pub struct Callbacks {
+    client: reqwest::Client,
+    secret: Option<String>,
+    max_retries: u32,
+    retry_base_delay: Duration,
+}
+
+impl Callbacks {
+    /// Fails if the HTTP client can't be built; a client without the attempt
+    /// timeout would let one hung webhook hold its lease forever
+    pub fn new(config: &Config) -> reqwest::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(ATTEMPT_TIMEOUT)
+                .build()?,
+            secret: config.callback_secret.clone(),
+            max_retries: config.callback_max_retries,
+            retry_base_delay: Duration::from_millis(config.callback_retry_base_ms),
+        })
+    }
+
+    /// Queue a callback for delivery by the background worker
+    pub async fn schedule<T: Serialize>(
+        &self,
+        queue: &Queue,
+        recording_id: &str,
+        url: &str,
+        payload: &T,
+    ) {
+        let body = match serde_json::to_string(payload) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Failed to serialize callback for {}: {}", url, e);
+                return;
+            }
+        };
+
+        if let Err(e) = queue.schedule_callback(recording_id, url, &body).await {
+            error!("Failed to schedule callback to {}: {}", url, e);
+        }
+    }
+
+    /// Attempt due callbacks until the process exits
+    pub async fn run_worker(&self, queue: &Queue) {
+        loop {
+            if !queue.is_available() {
+                tokio::time::sleep(POLL_INTERVAL).await;
+                continue;
+            }
+
+            match queue.claim_due_callbacks(CLAIM_LEASE, CLAIM_LIMIT).await {
+                Ok(due) if !due.is_empty() => {
+                    join_all(due.iter().map(|id| self.attempt(queue, id))).await;
+                }
+                Ok(_) => tokio::time::sleep(POLL_INTERVAL).await,
+                Err(e) => {
+                    error!("Failed to read due callbacks: {}", e);
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    /// Make one delivery attempt and record its outcome
+    async fn attempt(&self, queue: &Queue, recording_id: &str) {
+        let (delivery, body) = match (
+            queue.get_callback(recording_id).await,
+            queue.get_callback_body(recording_id).await,
+        ) {
+            (Ok(Some(delivery)), Ok(Some(body))) => (delivery, body),
+            (Ok(_), Ok(_)) => {
+                warn!("Callback for {} expired before delivery", recording_id);
+                if let Err(e) = queue.drop_callback(recording_id).await {
+                    error!("Failed to drop expired callback: {}", e);
+                }
+                return;
+            }
+            (Err(e), _) | (_, Err(e)) => {
+                // Still leased, so it is attempted again once the lease runs out
+                error!("Failed to load callback for {}: {}", recording_id, e);
+                return;
+            }
+        };
+        let url = &delivery.url;
+
+        let (status, failure, retry_in) = match self.post(url, body).await {
+            Ok(()) => {
+                info!("Delivered callback to {}", url);
+                ("delivered", None, None)
+            }
+            Err((failure, false)) => {
+                error!("Callback to {} rejected: {}; giving up", url, failure);
+                ("failed", Some(failure), None)
+            }
+            Err((failure, true)) if delivery.attempts >= self.max_retries => {
+                error!(
+                    "Callback to {} failed after {} attempts: {}",
+                    url,
+                    delivery.attempts + 1,
+                    failure
+                );
+                ("failed", Some(failure), None)
+            }
+            Err((failure, true)) => {
+                let delay = self.retry_base_delay * 2u32.saturating_pow(delivery.attempts);
+                warn!(
+                    "Callback to {} failed (attempt {}/{}): {}; retrying in {:?}",
+                    url,
+                    delivery.attempts + 1,
+                    self.max_retries,
+                    failure,
+                    delay
+                );
+                ("pending", Some(failure), Some(delay))
+            }
+        };
+
+        if let Err(e) = queue
+            .record_callback_attempt(recording_id, status, failure.as_deref(), retry_in)
+            .await
+        {
+            error!("Failed to record callback attempt: {}", e);
+        }
+    }
+
+    /// POST the body once. On failure returns the reason and whether it is
+    /// worth retrying.
+    async fn post(&self, url: &str, body: String) -> Result<(), (String, bool)> {
+        let signature = self
+            .secret
+            .as_deref()
+            .map(|secret| sign(secret, body.as_bytes()));
+
+        let mut request = self
+            .client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        if let Some(signature) = signature {
+            request = request.header(SIGNATURE_HEADER, signature);
+        }
+
+        match request.send().await {
+            Ok(response) if response.status().is_success() => Ok(()),
+            Ok(response) => Err((
+                format!("status {}", response.status()),
+                is_retryable(response.status()),
+            )),
+            Err(e) => Err((e.to_string(), true)),
+        }
+    }
+}
+
+/// HMAC-SHA256 of the body, formatted as `sha256=<hex>`
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Server errors and rate limiting are worth retrying; other client errors are not
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::REQUEST_TIMEOUT
+}
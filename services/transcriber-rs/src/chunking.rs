@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+use crate::engine::{TimedWord, Transcript, TranscriptionEngine};
+use crate::error::TranscribeError;
+use crate::transcriber::Profile;
+
+/// Window settings for transcribing long audio piece by piece
+#[derive(Debug, Clone, Copy)]
+pub struct ChunkConfig {
+    pub window_secs: f64,
+    pub overlap_secs: f64,
+}
+
+/// Window settings without a profile and for each profile; `None`
+/// transcribes in one pass
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProfileChunking {
+    pub default: Option<ChunkConfig>,
+    pub fast: Option<ChunkConfig>,
+    pub accurate: Option<ChunkConfig>,
+}
+
+impl ProfileChunking {
+    pub fn for_profile(&self, profile: Option<Profile>) -> Option<ChunkConfig> {
+        match profile {
+            None => self.default,
+            Some(Profile::Fast) => self.fast,
+            Some(Profile::Accurate) => self.accurate,
+        }
+    }
+}
+
+/// Words closer than this (seconds) with the same text are treated as one word
+/// seen from both sides of a window boundary
+const DUPLICATE_TOLERANCE_SECS: f64 = 0.3;
+
+/// Words stitched from the windows that were transcribed, and the error that
+/// stopped transcription early, if one did
+pub struct Windowed {
+    pub result: Transcript,
+    pub failure: Option<TranscribeError>,
+}
+
+/// Transcribe audio in overlapping windows and stitch the words back together
+/// with absolute timestamps. Windows are spread over `engines`, one thread per
+/// engine, so with several instances they run concurrently; with one they run
+/// one after another. Windows are stitched in order as they finish, and each
+/// window's words are handed to `on_window` right away, which can stop the
+/// pass early. A failing window (e.g. a corrupt tail) stops the pass but
+/// keeps the words from the windows before it.
+pub fn transcribe_windows(
+    engines: &mut [Box<dyn TranscriptionEngine>],
+    samples: Vec<f32>,
+    sample_rate: u32,
+    channels: u16,
+    config: &ChunkConfig,
+    mut on_window: impl FnMut(&[TimedWord]) -> ControlFlow<()>,
+) -> Windowed {
+    let frames = samples.len() / channels as usize;
+    let windows = windows(frames, sample_rate, config);
+    let next_window = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+
+    let mut words: Vec<TimedWord> = Vec::new();
+    let mut failure = None;
+    std::thread::scope(|scope| {
+        let (done_tx, done_rx) = mpsc::channel();
+        for engine in engines.iter_mut().take(windows.len()) {
+            let done_tx = done_tx.clone();
+            let (windows, samples, next_window, stop) = (&windows, &samples, &next_window, &stop);
+            scope.spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next_window.fetch_add(1, Ordering::Relaxed);
+                    let Some(&(start_frame, end_frame)) = windows.get(i) else {
+                        break;
+                    };
+                    let window = samples
+                        [start_frame * channels as usize..end_frame * channels as usize]
+                        .to_vec();
+                    if done_tx
+                        .send((i, engine.transcribe(window, sample_rate, channels)))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(done_tx);
+
+        // Windows can finish out of order; hold each until the ones before it
+        // have been stitched
+        let mut finished = BTreeMap::new();
+        let mut i = 0;
+        'receive: for (index, result) in done_rx {
+            finished.insert(index, result);
+            while let Some(result) = finished.remove(&i) {
+                let result = match result {
+                    Ok(result) => result,
+                    Err(e) => {
+                        failure = Some(e);
+                        break 'receive;
+                    }
+                };
+
+                let stitched = words.len();
+                stitch(&mut words, result.words, i, &windows, sample_rate, config);
+                if on_window(&words[stitched..]).is_break() {
+                    break 'receive;
+                }
+                i += 1;
+            }
+        }
+        // Let the other threads finish their current window and stop
+        stop.store(true, Ordering::Relaxed);
+    });
+
+    let text = words
+        .iter()
+        .map(|t| t.text.trim())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Windowed {
+        result: Transcript { text, words },
+        failure,
+    }
+}
+
+/// Append the words window `i` heard to `words`, shifted to absolute time and
+/// without the ones a neighbouring window owns
+fn stitch(
+    words: &mut Vec<TimedWord>,
+    window_words: Vec<TimedWord>,
+    i: usize,
+    windows: &[(usize, usize)],
+    sample_rate: u32,
+    config: &ChunkConfig,
+) {
+    let offset = windows[i].0 as f64 / sample_rate as f64;
+
+    // Each overlap is split at its midpoint: the earlier window owns words
+    // before it and the later window owns words after it
+    let keep_from = (i > 0).then(|| offset + config.overlap_secs / 2.0);
+    let keep_until = windows
+        .get(i + 1)
+        .map(|&(next_start, _)| next_start as f64 / sample_rate as f64 + config.overlap_secs / 2.0);
+
+    for mut word in window_words {
+        word.start += offset;
+        word.end += offset;
+
+        let midpoint = (word.start + word.end) / 2.0;
+        if keep_from.is_some_and(|from| midpoint < from)
+            || keep_until.is_some_and(|until| midpoint >= until)
+        {
+            continue;
+        }
+        if words.last().is_some_and(|last| is_duplicate(last, &word)) {
+            continue;
+        }
+        words.push(word);
+    }
+}
+
+/// Split `frames` into `(start, end)` frame ranges of `window_secs`, each
+/// starting `overlap_secs` before the previous one ends
+fn windows(frames: usize, sample_rate: u32, config: &ChunkConfig) -> Vec<(usize, usize)> {
+    let window = (config.window_secs * sample_rate as f64) as usize;
+    let overlap = (config.overlap_secs * sample_rate as f64) as usize;
+    let step = window.saturating_sub(overlap).max(1);
+
+    let mut windows = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + window).min(frames);
+        windows.push((start, end));
+        if end >= frames {
+            break;
+        }
+        start += step;
+    }
+    windows
+}
+
+/// A word repeated at nearly the same time on both sides of a window boundary
+fn is_duplicate(previous: &TimedWord, word: &TimedWord) -> bool {
+    previous.text.trim().eq_ignore_ascii_case(word.text.trim())
+        && (word.start - previous.start).abs() < DUPLICATE_TOLERANCE_SECS
+}
@@ -1,51 +1,757 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
 use std::env;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use tracing_subscriber::EnvFilter;
+
+use crate::chunking::{ChunkConfig, ProfileChunking};
+use crate::engine::Backend;
+
+/// Filler words removed by `remove_fillers`, per language
+const DEFAULT_FILLER_WORDS: &str = "en:um,uh,er,erm,hmm;de:äh,ähm,hm";
+/// Languages written without spaces between words
+const DEFAULT_UNSPACED_LANGUAGES: &str = "ja,zh,th,lo,km,my";
+
+/// Keys a config file must set (directly or via the environment)
+const REQUIRED_FILE_KEYS: &[&str] = &["MODEL_PATH", "MINIO_ENDPOINT", "MINIO_BUCKET", "REDIS_URL"];
 
 #[derive(Debug, Clone)]
 pub struct Config {
     // Server
+    /// Interface address to listen on
     pub host: String,
     pub port: u16,
+    /// Browser origins allowed to call the API, with credentials; any origin
+    /// (without credentials) when empty
+    pub cors_allowed_origins: Vec<String>,
+    /// Log filter (`info`, `debug`, or per-module directives like
+    /// `transcriber_rs=debug,tower_http=warn`); `RUST_LOG` overrides it
+    pub log_level: String,
+    /// `pretty` (human-readable) or `json` (one structured object per line)
+    pub log_format: String,
+    /// OTLP/HTTP collector base URL (e.g. `http://otel-collector:4318`);
+    /// traces are exported only when set
+    pub otlp_endpoint: Option<String>,
+    /// `service.name` reported on exported traces
+    pub otel_service_name: String,
 
     // MinIO/S3
     pub minio_endpoint: String,
     pub minio_access_key: String,
     pub minio_secret_key: String,
     pub minio_bucket: String,
+    /// Create the bucket at startup when it doesn't exist (for fresh dev
+    /// environments); otherwise a missing bucket fails startup
+    pub minio_create_bucket: bool,
     pub minio_use_ssl: bool,
+    /// Region used for request signing; must match the bucket's on AWS S3
+    pub s3_region: String,
+    /// Address buckets as `endpoint/bucket` (MinIO) rather than
+    /// `bucket.endpoint` (AWS S3)
+    pub s3_path_style: bool,
+    pub download_max_retries: u32,
+    pub download_retry_base_ms: u64,
 
     // Redis
     pub redis_url: String,
+    /// Retries of a Redis operation that failed to reach the server
+    pub redis_max_retries: u32,
+    pub redis_retry_base_ms: u64,
+    /// How long startup waits for Redis before serving without it (batch
+    /// endpoints answer 503 until it is reachable)
+    pub redis_startup_wait_secs: u64,
+    /// Namespace for every key, so environments can share one Redis
+    pub redis_key_prefix: String,
+    /// How long job statuses are kept
+    pub job_ttl_secs: u64,
+    /// How long per-recording results (and job result lists) are kept
+    pub result_ttl_secs: u64,
 
     // Model
+    /// Engine that loads and runs the model: `parakeet`
+    pub backend: String,
     pub model_path: String,
+    /// Weights to load when the model directory has several: `auto` (fp32
+    /// when present), `fp32` or `int8`
+    pub model_precision: String,
+    /// Directory scanned by `GET /models` for installed models
+    pub models_dir: String,
+    /// Language reported when a request doesn't specify one
+    pub default_language: Option<String>,
+    /// Defer loading the model until the first transcription request
+    pub lazy_load: bool,
+    /// Transcribe a short silent clip after loading so the first request is
+    /// warm; ignored with `lazy_load`
+    pub warmup_on_start: bool,
+    pub diarization_enabled: bool,
+    pub diarization_model_path: String,
+
+    // Audio conversion
+    pub audio_sample_rate: u32,
+    pub audio_channels: u16,
+    /// Extra ffmpeg filter chain (`-af`), e.g. `highpass=f=200`
+    pub audio_filter: Option<String>,
+    /// Normalize loudness during conversion; costs CPU on every file
+    pub audio_normalize: bool,
+    /// Reduce background noise during conversion; costs CPU on every file
+    pub audio_denoise: bool,
+    /// Largest gap (seconds) tolerated between the decoded length and the
+    /// source's own length before timestamps are considered drifted
+    pub drift_tolerance_secs: f64,
+    /// Stretch timestamps back onto the source's timeline when they drift;
+    /// otherwise drift is only logged
+    pub rescale_drift: bool,
+    /// Transcribe long audio in windows of this many seconds; one pass when unset
+    pub chunk_seconds: Option<f64>,
+    pub chunk_overlap_seconds: f64,
+    /// Window length for the `fast` profile; 0 transcribes in one pass
+    pub fast_chunk_seconds: f64,
+    pub fast_chunk_overlap_seconds: f64,
+    /// Window length for the `accurate` profile; CHUNK_SECONDS when unset,
+    /// 0 transcribes in one pass
+    pub accurate_chunk_seconds: Option<f64>,
+    /// CHUNK_OVERLAP_SECONDS when unset
+    pub accurate_chunk_overlap_seconds: Option<f64>,
+    /// Model instances that transcribe windows of one file concurrently; each
+    /// holds its own copy of the model in memory
+    pub window_workers: usize,
+    /// Directory for downloaded and uploaded audio; the system temp dir
+    /// (often a small tmpfs) when unset
+    pub temp_dir: Option<String>,
+
+    // Results
+    pub store_subtitles: bool,
+    /// Default silence gap (seconds) that starts a new segment; word-level when unset
+    pub segment_gap_secs: Option<f64>,
+    /// How long results are cached by audio content hash; 0 disables the cache
+    pub cache_ttl_secs: u64,
+    /// How long responses are kept for replay under an idempotency key
+    pub idempotency_ttl_secs: u64,
+    /// Filler words per language (`FILLER_WORDS=en:um,uh;de:äh`)
+    pub filler_words: HashMap<String, Vec<String>>,
+    /// Silence (seconds) below which `merge_gaps` joins adjacent segments
+    pub merge_gap_secs: f64,
+    /// Level (dBFS) below which `drop_silence_hallucinations` drops a word
+    pub silence_threshold_db: f32,
+    /// Pause (seconds) that ends a sentence, for `restore_punctuation` and
+    /// sentence segments
+    pub punctuation_pause_secs: f64,
+    /// Trim segment texts and collapse whitespace in the returned text
+    pub normalize_text: bool,
+    /// Languages whose text is normalized without spaces between words
+    pub unspaced_languages: Vec<String>,
+    /// Seconds of transcript, from the first word, that `auto_detect_language`
+    /// identifies the language from
+    pub language_detect_secs: f64,
+    /// Lifetime of presigned download URLs for stored result JSON
+    pub result_url_expiry_secs: u32,
+
+    // Limits
+    pub max_concurrent_transcriptions: usize,
+    /// Threads for blocking work (ffmpeg, model loading, inference). Inference
+    /// is serialized by the model mutex, so threads beyond one per concurrent
+    /// transcription only wait on it; extra threads serve ffmpeg and file I/O.
+    /// Defaults to the number of CPUs.
+    pub blocking_threads: usize,
+    /// Workers per pod pulling from the shared batch queue
+    pub batch_workers: usize,
+    /// Upper bound on a single inference run; unlimited when unset
+    pub transcription_timeout_secs: Option<u64>,
+    /// Longest audio accepted for transcription, checked after conversion and
+    /// before inference; unlimited when unset
+    pub max_audio_seconds: Option<f64>,
+    /// How long a request waits for a free transcription slot before giving up
+    pub permit_timeout_secs: u64,
+    pub max_upload_bytes: usize,
+    /// Uploads up to this size are kept in memory, and WAV ones already in
+    /// the model's format skip the temp file and ffmpeg; 0 always uses a file
+    pub in_memory_max_bytes: usize,
+    /// Batch results whose JSON is larger than this are written to the
+    /// bucket, with only a reference kept in Redis
+    pub max_inline_result_bytes: usize,
+    /// Largest audio file fetched from MinIO or an HTTP(S) URL
+    pub max_download_bytes: u64,
+    /// How long connecting to an HTTP(S) audio URL may take
+    pub download_connect_timeout_secs: u64,
+    /// Longest an HTTP(S) download may go without receiving any data
+    pub download_read_timeout_secs: u64,
+    /// Upper bound on a whole HTTP(S) download
+    pub download_timeout_secs: u64,
+    /// Most files accepted by one `POST /transcribe/multi` call
+    pub max_multi_files: usize,
+    /// Most recordings accepted by one `POST /transcribe/batch` call
+    pub max_batch_size: usize,
+    /// How long `POST /transcribe/batch?wait=true` may run
+    pub batch_wait_timeout_secs: u64,
+
+    // Callbacks
+    /// Shared secret used to sign callback bodies; unsigned when unset
+    pub callback_secret: Option<String>,
+    pub callback_max_retries: u32,
+    pub callback_retry_base_ms: u64,
+
+    // Auth
+    /// Key required in `X-API-Key` on every non-probe route; open when unset
+    pub api_key: Option<String>,
+    /// Requests per minute allowed per client; unlimited when unset
+    pub rate_limit_per_minute: Option<u32>,
+    /// Requests a client can burst after being idle (defaults to the per-minute rate)
+    pub rate_limit_burst: Option<u32>,
+    /// Token required by admin endpoints; they are disabled when unset
+    pub admin_token: Option<String>,
 }
 
 impl Config {
     pub fn from_env() -> Self {
+        Self::from_lookup(|key| env::var(key))
+    }
+
+    /// Load settings from a TOML (`.toml`) or JSON file whose keys are the
+    /// environment variable names, case-insensitive. Environment variables
+    /// still override individual keys from the file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+
+        let is_toml = path
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("toml"));
+        let values = if is_toml {
+            let table: toml::Table = toml::from_str(&contents)
+                .with_context(|| format!("Invalid TOML in {}", path.display()))?;
+            table
+                .into_iter()
+                .map(|(key, value)| Ok((key.to_uppercase(), toml_scalar(&key, value)?)))
+                .collect::<Result<HashMap<_, _>>>()?
+        } else {
+            let object: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_str(&contents)
+                    .with_context(|| format!("Invalid JSON in {}", path.display()))?;
+            object
+                .into_iter()
+                .map(|(key, value)| Ok((key.to_uppercase(), json_scalar(&key, value)?)))
+                .collect::<Result<HashMap<_, _>>>()?
+        };
+
+        let lookup = |key: &str| {
+            env::var(key).or_else(|_| values.get(key).cloned().ok_or(env::VarError::NotPresent))
+        };
+
+        // Deployments should pin these rather than fall back to dev defaults
+        let missing: Vec<&str> = REQUIRED_FILE_KEYS
+            .iter()
+            .copied()
+            .filter(|key| lookup(key).is_err())
+            .collect();
+        if !missing.is_empty() {
+            bail!(
+                "Config file {} is missing required keys: {}",
+                path.display(),
+                missing.join(", ")
+            );
+        }
+
+        Ok(Self::from_lookup(lookup))
+    }
+
+    fn from_lookup(var: impl Fn(&str) -> std::result::Result<String, env::VarError>) -> Self {
         Self {
-            host: env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
-            port: env::var("PORT")
+            host: var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string()),
+            port: var("PORT")
                 .ok()
                 .and_then(|p| p.parse().ok())
                 .unwrap_or(8000),
+            cors_allowed_origins: var("CORS_ALLOWED_ORIGINS")
+                .map(|v| {
+                    v.split(',')
+                        .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                        .filter(|origin| !origin.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            log_level: var("LOG_LEVEL")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| "info".to_string()),
+            log_format: var("LOG_FORMAT")
+                .map(|v| v.to_lowercase())
+                .unwrap_or_else(|_| "pretty".to_string()),
+            otlp_endpoint: var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .ok()
+                .filter(|v| !v.is_empty()),
+            otel_service_name: var("OTEL_SERVICE_NAME")
+                .unwrap_or_else(|_| "transcriber-rs".to_string()),
 
-            minio_endpoint: env::var("MINIO_ENDPOINT")
-                .unwrap_or_else(|_| "minio:9000".to_string()),
-            minio_access_key: env::var("MINIO_ACCESS_KEY")
-                .unwrap_or_else(|_| "minioadmin".to_string()),
-            minio_secret_key: env::var("MINIO_SECRET_KEY")
+            minio_endpoint: var("MINIO_ENDPOINT").unwrap_or_else(|_| "minio:9000".to_string()),
+            minio_access_key: var("MINIO_ACCESS_KEY").unwrap_or_else(|_| "minioadmin".to_string()),
+            minio_secret_key: var("MINIO_SECRET_KEY")
                 .unwrap_or_else(|_| "minioadmin123".to_string()),
-            minio_bucket: env::var("MINIO_BUCKET")
-                .unwrap_or_else(|_| "recordings".to_string()),
-            minio_use_ssl: env::var("MINIO_USE_SSL")
+            minio_bucket: var("MINIO_BUCKET").unwrap_or_else(|_| "recordings".to_string()),
+            minio_create_bucket: var("MINIO_CREATE_BUCKET")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            minio_use_ssl: var("MINIO_USE_SSL")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            s3_region: var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+            s3_path_style: var("S3_PATH_STYLE")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(true),
+            download_max_retries: var("DOWNLOAD_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            download_retry_base_ms: var("DOWNLOAD_RETRY_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(500),
+
+            redis_url: var("REDIS_URL").unwrap_or_else(|_| "redis://redis:6379".to_string()),
+            redis_max_retries: var("REDIS_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            redis_retry_base_ms: var("REDIS_RETRY_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100),
+            redis_startup_wait_secs: var("REDIS_STARTUP_WAIT_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            redis_key_prefix: var("REDIS_KEY_PREFIX")
+                .map(|v| v.trim_end_matches(':').to_string())
+                .unwrap_or_else(|_| "transcribe".to_string()),
+            job_ttl_secs: var("JOB_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(86400),
+            result_ttl_secs: var("RESULT_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(604800),
+
+            backend: var("BACKEND")
+                .map(|v| v.to_lowercase())
+                .unwrap_or_else(|_| "parakeet".to_string()),
+            model_path: var("MODEL_PATH").unwrap_or_else(|_| "./models/parakeet-v3".to_string()),
+            model_precision: var("MODEL_PRECISION").unwrap_or_else(|_| "auto".to_string()),
+            models_dir: var("MODELS_DIR").unwrap_or_else(|_| "./models".to_string()),
+            default_language: var("DEFAULT_LANGUAGE").ok().filter(|v| !v.is_empty()),
+            lazy_load: var("LAZY_LOAD")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            warmup_on_start: var("WARMUP_ON_START")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            diarization_enabled: var("ENABLE_DIARIZATION")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            diarization_model_path: var("DIARIZATION_MODEL_PATH")
+                .unwrap_or_else(|_| "./models/diar_streaming_sortformer_4spk-v2.onnx".to_string()),
+
+            audio_sample_rate: var("AUDIO_SAMPLE_RATE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(16000),
+            audio_channels: var("AUDIO_CHANNELS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1),
+            audio_filter: var("AUDIO_FILTER").ok().filter(|v| !v.is_empty()),
+            audio_normalize: var("AUDIO_NORMALIZE")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            audio_denoise: var("AUDIO_DENOISE")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            drift_tolerance_secs: var("DRIFT_TOLERANCE_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.25),
+            rescale_drift: var("RESCALE_DRIFT")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            chunk_seconds: var("CHUNK_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &f64| *v > 0.0),
+            chunk_overlap_seconds: var("CHUNK_OVERLAP_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2.0),
+            fast_chunk_seconds: var("FAST_CHUNK_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30.0),
+            fast_chunk_overlap_seconds: var("FAST_CHUNK_OVERLAP_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.5),
+            accurate_chunk_seconds: var("ACCURATE_CHUNK_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            accurate_chunk_overlap_seconds: var("ACCURATE_CHUNK_OVERLAP_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            window_workers: var("WINDOW_WORKERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v > 0)
+                .unwrap_or(1),
+            temp_dir: var("TEMP_DIR").ok().filter(|v| !v.is_empty()),
+
+            store_subtitles: var("STORE_SUBTITLES")
                 .map(|v| v == "true" || v == "1")
                 .unwrap_or(false),
+            segment_gap_secs: var("SEGMENT_GAP_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &f64| *v > 0.0),
+            filler_words: parse_filler_words(
+                &var("FILLER_WORDS").unwrap_or_else(|_| DEFAULT_FILLER_WORDS.to_string()),
+            ),
+            merge_gap_secs: var("MERGE_GAP_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1.0),
+            silence_threshold_db: var("SILENCE_THRESHOLD_DB")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(-50.0),
+            punctuation_pause_secs: var("PUNCTUATION_PAUSE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0.7),
+            normalize_text: var("NORMALIZE_TEXT")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(true),
+            unspaced_languages: var("UNSPACED_LANGUAGES")
+                .unwrap_or_else(|_| DEFAULT_UNSPACED_LANGUAGES.to_string())
+                .split(',')
+                .map(|language| language.trim().to_lowercase())
+                .filter(|language| !language.is_empty())
+                .collect(),
+            language_detect_secs: var("LANGUAGE_DETECT_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30.0),
+            cache_ttl_secs: var("CACHE_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(604800),
+            idempotency_ttl_secs: var("IDEMPOTENCY_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(86400),
+            result_url_expiry_secs: var("RESULT_URL_EXPIRY_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(900),
+
+            blocking_threads: var("BLOCKING_THREADS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or_else(|| {
+                    std::thread::available_parallelism()
+                        .map(|n| n.get())
+                        .unwrap_or(4)
+                }),
+            max_concurrent_transcriptions: var("MAX_CONCURRENT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v: &usize| *v > 0)
+                .unwrap_or(4),
+            batch_workers: var("BATCH_WORKERS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(4),
+            transcription_timeout_secs: var("TRANSCRIPTION_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v > 0),
+            max_audio_seconds: var("MAX_AUDIO_SECONDS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v > 0.0),
+            permit_timeout_secs: var("PERMIT_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            batch_wait_timeout_secs: var("BATCH_WAIT_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(600),
+            max_multi_files: var("MAX_MULTI_FILES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            max_batch_size: var("MAX_BATCH_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+            in_memory_max_bytes: var("IN_MEMORY_MAX_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1024 * 1024),
+            max_inline_result_bytes: var("MAX_INLINE_RESULT_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1024 * 1024),
+            max_upload_bytes: var("MAX_UPLOAD_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(200 * 1024 * 1024),
+            max_download_bytes: var("MAX_DOWNLOAD_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(500 * 1024 * 1024),
+            download_connect_timeout_secs: var("DOWNLOAD_CONNECT_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10),
+            download_read_timeout_secs: var("DOWNLOAD_READ_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
+            download_timeout_secs: var("DOWNLOAD_TIMEOUT_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(600),
+
+            callback_secret: var("CALLBACK_SECRET").ok().filter(|v| !v.is_empty()),
+            callback_max_retries: var("CALLBACK_RETRIES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+            callback_retry_base_ms: var("CALLBACK_RETRY_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+
+            api_key: var("API_KEY").ok().filter(|v| !v.is_empty()),
+            rate_limit_per_minute: var("RATE_LIMIT_PER_MINUTE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v > 0),
+            rate_limit_burst: var("RATE_LIMIT_BURST")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|v| *v > 0),
+            admin_token: var("ADMIN_TOKEN").ok().filter(|v| !v.is_empty()),
+        }
+    }
+
+    /// Window settings for requests without a profile and for each profile
+    pub fn profile_chunking(&self) -> ProfileChunking {
+        ProfileChunking {
+            default: self.chunk_config(),
+            fast: self.fast_chunk_config(),
+            accurate: self.accurate_chunk_config(),
+        }
+    }
+
+    fn chunk_config(&self) -> Option<ChunkConfig> {
+        self.chunk_seconds.map(|window_secs| ChunkConfig {
+            window_secs,
+            overlap_secs: self.chunk_overlap_seconds,
+        })
+    }
+
+    fn fast_chunk_config(&self) -> Option<ChunkConfig> {
+        (self.fast_chunk_seconds > 0.0).then_some(ChunkConfig {
+            window_secs: self.fast_chunk_seconds,
+            overlap_secs: self.fast_chunk_overlap_seconds,
+        })
+    }
+
+    fn accurate_chunk_config(&self) -> Option<ChunkConfig> {
+        self.accurate_chunk_seconds
+            .or(self.chunk_seconds)
+            .filter(|window_secs| *window_secs > 0.0)
+            .map(|window_secs| ChunkConfig {
+                window_secs,
+                overlap_secs: self
+                    .accurate_chunk_overlap_seconds
+                    .unwrap_or(self.chunk_overlap_seconds),
+            })
+    }
+
+    /// Reject settings that would otherwise fail much later, or silently
+    /// produce garbage, at request time. Every problem is reported at once.
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if self.host.parse::<IpAddr>().is_err() {
+            problems.push(format!(
+                "HOST must be an IP address (e.g. 0.0.0.0 or 127.0.0.1), got {}",
+                self.host
+            ));
+        }
+        if self.port == 0 {
+            problems.push("PORT must be between 1 and 65535".to_string());
+        }
+        for origin in &self.cors_allowed_origins {
+            // An origin is a scheme, host and port only; browsers never send a path
+            match reqwest::Url::parse(origin) {
+                Ok(url) if url.origin().ascii_serialization() == *origin => {}
+                _ => problems.push(format!(
+                    "CORS_ALLOWED_ORIGINS entries must be origins like https://app.example.com, got {}",
+                    origin
+                )),
+            }
+        }
+        if !self.drift_tolerance_secs.is_finite() || self.drift_tolerance_secs < 0.0 {
+            problems.push("DRIFT_TOLERANCE_SECONDS must not be negative".to_string());
+        }
+        if self.blocking_threads == 0 {
+            problems.push("BLOCKING_THREADS must be positive".to_string());
+        }
+        if Backend::parse(&self.backend).is_none() {
+            problems.push(format!("BACKEND must be parakeet, got {}", self.backend));
+        }
+        match self.model_precision.as_str() {
+            "auto" | "fp32" | "int8" => {}
+            "fp16" => problems.push(
+                "MODEL_PRECISION=fp16 is not supported: Parakeet TDT models ship fp32 and int8 weights"
+                    .to_string(),
+            ),
+            other => problems.push(format!(
+                "MODEL_PRECISION must be auto, fp32 or int8, got {}",
+                other
+            )),
+        }
+        if let Err(e) = EnvFilter::try_new(&self.log_level) {
+            problems.push(format!("LOG_LEVEL {} is invalid: {}", self.log_level, e));
+        }
+        if !matches!(self.log_format.as_str(), "pretty" | "json") {
+            problems.push(format!(
+                "LOG_FORMAT must be pretty or json, got {}",
+                self.log_format
+            ));
+        }
+        if self.minio_bucket.trim().is_empty() {
+            problems.push("MINIO_BUCKET must not be empty".to_string());
+        }
+        if let Err(e) = redis::Client::open(self.redis_url.as_str()) {
+            problems.push(format!("REDIS_URL is invalid: {}", e));
+        }
+        if self.redis_key_prefix.is_empty() {
+            problems.push("REDIS_KEY_PREFIX must not be empty".to_string());
+        }
+        if self.job_ttl_secs == 0 || self.result_ttl_secs == 0 {
+            problems.push("JOB_TTL_SECS and RESULT_TTL_SECS must be positive".to_string());
+        }
+        if self.model_path.trim().is_empty() {
+            problems.push("MODEL_PATH must not be empty".to_string());
+        } else if !Path::new(&self.model_path).exists() {
+            problems.push(format!("MODEL_PATH {} does not exist", self.model_path));
+        }
+        if self.diarization_enabled && !Path::new(&self.diarization_model_path).exists() {
+            problems.push(format!(
+                "DIARIZATION_MODEL_PATH {} does not exist",
+                self.diarization_model_path
+            ));
+        }
+        if let Some(ref temp_dir) = self.temp_dir {
+            if let Err(e) = tempfile::tempfile_in(temp_dir) {
+                problems.push(format!(
+                    "TEMP_DIR {} is not a writable directory: {}",
+                    temp_dir, e
+                ));
+            }
+        }
+        if !(8000..=192000).contains(&self.audio_sample_rate) {
+            problems.push(format!(
+                "AUDIO_SAMPLE_RATE must be between 8000 and 192000, got {}",
+                self.audio_sample_rate
+            ));
+        }
+        if !(1..=2).contains(&self.audio_channels) {
+            problems.push(format!(
+                "AUDIO_CHANNELS must be 1 or 2, got {}",
+                self.audio_channels
+            ));
+        }
+        for (name, chunking) in [
+            ("CHUNK", self.chunk_config()),
+            ("FAST_CHUNK", self.fast_chunk_config()),
+            ("ACCURATE_CHUNK", self.accurate_chunk_config()),
+        ] {
+            if let Some(chunk) = chunking {
+                if !(0.0..chunk.window_secs).contains(&chunk.overlap_secs) {
+                    problems.push(format!(
+                        "{}_OVERLAP_SECONDS must be at least 0 and less than {}_SECONDS ({}), got {}",
+                        name, name, chunk.window_secs, chunk.overlap_secs
+                    ));
+                }
+            }
+        }
+        if self.diarization_enabled && self.audio_sample_rate != 16000 {
+            problems.push("Diarization requires AUDIO_SAMPLE_RATE=16000".to_string());
+        }
+        if self.merge_gap_secs.is_nan() || self.merge_gap_secs <= 0.0 {
+            problems.push("MERGE_GAP_SECS must be positive".to_string());
+        }
+        if self.batch_wait_timeout_secs == 0 {
+            problems.push("BATCH_WAIT_TIMEOUT_SECS must be positive".to_string());
+        }
+        if self.silence_threshold_db.is_nan() || self.silence_threshold_db > 0.0 {
+            problems.push("SILENCE_THRESHOLD_DB must be at most 0 (dBFS)".to_string());
+        }
+        if self.punctuation_pause_secs.is_nan() || self.punctuation_pause_secs <= 0.0 {
+            problems.push("PUNCTUATION_PAUSE_SECS must be positive".to_string());
+        }
+        if self.language_detect_secs.is_nan() || self.language_detect_secs <= 0.0 {
+            problems.push("LANGUAGE_DETECT_SECONDS must be positive".to_string());
+        }
+        // S3 presigned URLs are valid for at most seven days
+        if !(1..=604800).contains(&self.result_url_expiry_secs) {
+            problems.push(format!(
+                "RESULT_URL_EXPIRY_SECS must be between 1 and 604800, got {}",
+                self.result_url_expiry_secs
+            ));
+        }
 
-            redis_url: env::var("REDIS_URL")
-                .unwrap_or_else(|_| "redis://redis:6379".to_string()),
+        if !problems.is_empty() {
+            bail!("Invalid configuration:\n  - {}", problems.join("\n  - "));
+        }
+        Ok(())
+    }
+}
+
+/// Parse `lang:word,word;lang:word` into lowercase word lists per language
+fn parse_filler_words(value: &str) -> HashMap<String, Vec<String>> {
+    value
+        .split(';')
+        .filter_map(|entry| entry.split_once(':'))
+        .map(|(language, words)| {
+            let words = words
+                .split(',')
+                .map(|w| w.trim().to_lowercase())
+                .filter(|w| !w.is_empty())
+                .collect();
+            (language.trim().to_lowercase(), words)
+        })
+        .collect()
+}
 
-            model_path: env::var("MODEL_PATH")
-                .unwrap_or_else(|_| "./models/parakeet-v3".to_string()),
+fn toml_scalar(key: &str, value: toml::Value) -> Result<String> {
+    match value {
+        toml::Value::String(s) => Ok(s),
+        toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+            Ok(value.to_string())
         }
+        _ => bail!("Config key {} must be a string, number or boolean", key),
+    }
+}
+
+fn json_scalar(key: &str, value: serde_json::Value) -> Result<String> {
+    match value {
+        serde_json::Value::String(s) => Ok(s),
+        serde_json::Value::Number(_) | serde_json::Value::Bool(_) => Ok(value.to_string()),
+        _ => bail!("Config key {} must be a string, number or boolean", key),
     }
 }
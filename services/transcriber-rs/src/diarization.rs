@@ -0,0 +1,60 @@
+use parakeet_rs::sortformer::{DiarizationConfig, Sortformer, SpeakerSegment};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::error::{Result, TranscribeError};
+use crate::transcriber::Segment;
+
+/// Speaker diarization backed by NVIDIA Sortformer (up to 4 speakers)
+pub struct Diarizer {
+    engine: Mutex<Sortformer>,
+}
+
+impl Diarizer {
+    /// Load the Sortformer ONNX model in a blocking task
+    pub async fn load(model_path: &Path) -> Result<Self> {
+        let model_path = model_path.to_path_buf();
+
+        tokio::task::spawn_blocking(move || {
+            let engine = Sortformer::with_config(&model_path, None, DiarizationConfig::default())
+                .map_err(|e| {
+                TranscribeError::Internal(format!("Failed to load diarization model: {}", e))
+            })?;
+            Ok(Self {
+                engine: Mutex::new(engine),
+            })
+        })
+        .await?
+    }
+
+    /// Find speaker turns in decoded samples. Blocking; call from a blocking task.
+    pub fn diarize(
+        &self,
+        samples: Vec<f32>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<Vec<SpeakerSegment>> {
+        self.engine
+            .lock()
+            .unwrap()
+            .diarize(samples, sample_rate, channels)
+            .map_err(|e| TranscribeError::TranscriptionFailed(format!("Diarization failed: {}", e)))
+    }
+}
+
+/// Label each segment with the speaker whose turn overlaps it the most;
+/// segments that overlap no turn keep `speaker: None`.
+pub fn assign_speakers(segments: &mut [Segment], turns: &[SpeakerSegment]) {
+    for segment in segments {
+        segment.speaker = turns
+            .iter()
+            .map(|turn| {
+                let overlap =
+                    segment.end.min(turn.end as f64) - segment.start.max(turn.start as f64);
+                (turn.speaker_id, overlap)
+            })
+            .filter(|(_, overlap)| *overlap > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(speaker_id, _)| format!("speaker_{}", speaker_id));
+    }
+}
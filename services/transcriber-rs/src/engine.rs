@@ -0,0 +1,321 @@
+use parakeet_rs::{ParakeetTDT, TimestampMode, Transcriber as ParakeetTranscriber};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, TranscribeError};
+
+/// A speech recognition model: turns samples into timed words. Audio
+/// conversion, chunking, diarization and timeouts are handled by
+/// `Transcriber` the same way for every engine.
+///
+/// Loading and inference are CPU-bound and blocking; callers run them on the
+/// blocking pool.
+pub trait TranscriptionEngine: Send {
+    /// Load the model in `model_path`, returning the precision of the
+    /// weights that were loaded
+    fn load_model(&mut self, model_path: &Path) -> Result<ModelPrecision>;
+
+    /// Whether a model is loaded and can transcribe
+    fn is_ready(&self) -> bool;
+
+    /// Transcribe interleaved samples, timing words from the start of `samples`
+    fn transcribe(
+        &mut self,
+        samples: Vec<f32>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<Transcript>;
+}
+
+/// What an engine heard in one pass
+#[derive(Debug, Clone, Default)]
+pub struct Transcript {
+    pub text: String,
+    pub words: Vec<TimedWord>,
+}
+
+/// A word with its time (seconds) in the audio it was transcribed from
+#[derive(Debug, Clone)]
+pub struct TimedWord {
+    pub text: String,
+    pub start: f64,
+    pub end: f64,
+    /// Model confidence, when the engine provides one
+    pub confidence: Option<f32>,
+    /// Language of the word, for engines that tell languages apart within
+    /// one transcript
+    pub language: Option<String>,
+}
+
+/// Engine implementations compiled into this build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// NVIDIA Parakeet TDT through parakeet-rs
+    #[default]
+    Parakeet,
+}
+
+impl Backend {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "parakeet" => Some(Self::Parakeet),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Parakeet => "parakeet",
+        }
+    }
+
+    /// A new engine with no model loaded yet
+    pub fn create(
+        self,
+        precision: ModelPrecision,
+        temp_dir: &Path,
+    ) -> Box<dyn TranscriptionEngine> {
+        match self {
+            Self::Parakeet => Box::new(ParakeetEngine::new(precision, temp_dir)),
+        }
+    }
+}
+
+/// Which engine to create, which weights it loads, and where loading may
+/// stage files
+#[derive(Debug, Clone)]
+pub struct EngineSpec {
+    pub backend: Backend,
+    pub precision: ModelPrecision,
+    pub temp_dir: PathBuf,
+}
+
+impl EngineSpec {
+    /// A new engine with no model loaded yet
+    pub fn create(&self) -> Box<dyn TranscriptionEngine> {
+        self.backend.create(self.precision, &self.temp_dir)
+    }
+}
+
+/// Which weights to load from a model directory holding several variants
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModelPrecision {
+    /// fp32 when present, otherwise int8
+    #[default]
+    Auto,
+    Fp32,
+    /// Quantized weights: faster on CPU, slightly less accurate
+    Int8,
+}
+
+impl ModelPrecision {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(Self::Auto),
+            "fp32" => Some(Self::Fp32),
+            "int8" => Some(Self::Int8),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Fp32 => "fp32",
+            Self::Int8 => "int8",
+        }
+    }
+
+    /// Encoder and decoder-joint file names of this precision's weights
+    fn files(self) -> &'static [&'static str] {
+        match self {
+            Self::Auto => &[],
+            Self::Fp32 => &["encoder-model.onnx", "decoder_joint-model.onnx"],
+            Self::Int8 => &["encoder-model.int8.onnx", "decoder_joint-model.int8.onnx"],
+        }
+    }
+
+    fn is_present_in(self, model_path: &Path) -> bool {
+        self.files()
+            .iter()
+            .all(|file| model_path.join(file).is_file())
+    }
+}
+
+/// Parakeet TDT, which identifies the spoken language on its own
+pub struct ParakeetEngine {
+    model: Option<ParakeetTDT>,
+    precision: ModelPrecision,
+    /// Where a model directory is staged when only some of its weights load
+    temp_dir: PathBuf,
+}
+
+impl ParakeetEngine {
+    pub fn new(precision: ModelPrecision, temp_dir: &Path) -> Self {
+        Self {
+            model: None,
+            precision,
+            temp_dir: temp_dir.to_path_buf(),
+        }
+    }
+}
+
+impl TranscriptionEngine for ParakeetEngine {
+    fn load_model(&mut self, model_path: &Path) -> Result<ModelPrecision> {
+        check_model_dir(model_path)?;
+
+        let precision = match self.precision {
+            ModelPrecision::Auto if ModelPrecision::Fp32.is_present_in(model_path) => {
+                ModelPrecision::Fp32
+            }
+            ModelPrecision::Auto if ModelPrecision::Int8.is_present_in(model_path) => {
+                ModelPrecision::Int8
+            }
+            ModelPrecision::Auto => ModelPrecision::Auto,
+            precision if precision.is_present_in(model_path) => precision,
+            precision => {
+                return Err(TranscribeError::Internal(format!(
+                    "MODEL_PRECISION={} needs {} in {}",
+                    precision.as_str(),
+                    precision.files().join(" and "),
+                    model_path.display()
+                )))
+            }
+        };
+
+        // The loader prefers fp32 weights over int8 ones, so int8 weights
+        // next to fp32 ones are loaded from a directory linking only them
+        let staged = if precision == ModelPrecision::Int8
+            && ModelPrecision::Fp32.is_present_in(model_path)
+        {
+            Some(stage_model(
+                model_path,
+                ModelPrecision::Int8,
+                &self.temp_dir,
+            )?)
+        } else {
+            None
+        };
+        let load_path = staged.as_ref().map_or(model_path, |dir| dir.path());
+
+        let model = ParakeetTDT::from_pretrained(load_path, None).map_err(|e| {
+            TranscribeError::Internal(format!("Failed to load Parakeet TDT model: {}", e))
+        })?;
+        self.model = Some(model);
+        Ok(precision)
+    }
+
+    fn is_ready(&self) -> bool {
+        self.model.is_some()
+    }
+
+    fn transcribe(
+        &mut self,
+        samples: Vec<f32>,
+        sample_rate: u32,
+        channels: u16,
+    ) -> Result<Transcript> {
+        let model = self.model.as_mut().ok_or(TranscribeError::ModelNotReady)?;
+        let result = model
+            .transcribe_samples(samples, sample_rate, channels, Some(TimestampMode::Words))
+            .map_err(|e| TranscribeError::TranscriptionFailed(e.to_string()))?;
+
+        // parakeet-rs tokens carry no probability or language, so both are unknown
+        Ok(Transcript {
+            text: result.text,
+            words: result
+                .tokens
+                .into_iter()
+                .map(|token| TimedWord {
+                    text: token.text,
+                    start: token.start as f64,
+                    end: token.end as f64,
+                    confidence: None,
+                    language: None,
+                })
+                .collect(),
+        })
+    }
+}
+
+/// Fail clearly when `model_path` is missing, or doesn't hold a complete
+/// Parakeet TDT model (e.g. after an interrupted download), instead of deep
+/// inside the loader
+fn check_model_dir(model_path: &Path) -> Result<()> {
+    if !model_path.exists() {
+        return Err(TranscribeError::ModelNotFound(
+            model_path.display().to_string(),
+        ));
+    }
+
+    let files: Vec<String> = std::fs::read_dir(model_path)
+        .map_err(|e| {
+            TranscribeError::InvalidModel(format!(
+                "{} is not a readable directory: {}",
+                model_path.display(),
+                e
+            ))
+        })?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .collect();
+
+    // The loader also accepts other names for the weights, so match loosely
+    let is_weights = |file: &str, prefix: &str| {
+        file.starts_with(prefix) && (file.ends_with(".onnx") || file.ends_with(".onnx.data"))
+    };
+    let mut missing = Vec::new();
+    if !files.iter().any(|file| file == "vocab.txt") {
+        missing.push("vocab.txt");
+    }
+    if !files.iter().any(|file| is_weights(file, "encoder")) {
+        missing.push("encoder-model.onnx");
+    }
+    if !files.iter().any(|file| is_weights(file, "decoder")) {
+        missing.push("decoder_joint-model.onnx");
+    }
+    if !missing.is_empty() {
+        return Err(TranscribeError::InvalidModel(format!(
+            "{} doesn't look like a Parakeet TDT model: missing {}",
+            model_path.display(),
+            missing.join(", ")
+        )));
+    }
+
+    // A truncated download leaves empty files behind
+    for file in files.iter().filter(|file| {
+        *file == "vocab.txt" || is_weights(file, "encoder") || is_weights(file, "decoder")
+    }) {
+        let path = model_path.join(file);
+        match std::fs::File::open(&path).and_then(|f| f.metadata()) {
+            Ok(metadata) if metadata.len() > 0 => {}
+            Ok(_) => {
+                return Err(TranscribeError::InvalidModel(format!(
+                    "{} is empty; the model download may be incomplete",
+                    path.display()
+                )))
+            }
+            Err(e) => {
+                return Err(TranscribeError::InvalidModel(format!(
+                    "Cannot read {}: {}",
+                    path.display(),
+                    e
+                )))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// A temporary model directory in `temp_dir` with links to the vocabulary
+/// and to one precision's weights. Only needed while loading.
+fn stage_model(
+    model_path: &Path,
+    precision: ModelPrecision,
+    temp_dir: &Path,
+) -> Result<tempfile::TempDir> {
+    let staged = tempfile::tempdir_in(temp_dir)?;
+    for file in precision.files().iter().chain(&["vocab.txt"]) {
+        std::os::unix::fs::symlink(model_path.join(file), staged.path().join(file))?;
+    }
+    Ok(staged)
+}
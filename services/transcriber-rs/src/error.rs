@@ -0,0 +1,204 @@
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use thiserror::Error;
+use tracing::error;
+
+use crate::handlers::ErrorResponse;
+
+pub type Result<T> = std::result::Result<T, TranscribeError>;
+
+#[derive(Debug, Error)]
+pub enum TranscribeError {
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Audio file not found")]
+    AudioNotFound,
+
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+
+    #[error("Unsupported: {0}")]
+    Unsupported(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Payload too large: {0}")]
+    TooLarge(String),
+
+    #[error("Transcriber not ready")]
+    ModelNotReady,
+
+    #[error("Model not found at {0}; set MODEL_PATH")]
+    ModelNotFound(String),
+
+    #[error("Invalid model: {0}")]
+    InvalidModel(String),
+
+    #[error("Too many concurrent transcriptions, try again later")]
+    Overloaded,
+
+    #[error("Rate limit exceeded, retry after {0} seconds")]
+    RateLimited(u64),
+
+    #[error("Audio conversion failed: {0}")]
+    ConversionFailed(String),
+
+    #[error("Transcription failed: {0}")]
+    TranscriptionFailed(String),
+
+    #[error("Timed out after {0} seconds")]
+    Timeout(u64),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Transcription cancelled")]
+    Cancelled,
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    #[error("Queue error: {0}")]
+    Queue(String),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl TranscribeError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::AudioNotFound => StatusCode::NOT_FOUND,
+            Self::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            Self::Unsupported(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            Self::Forbidden(_) => StatusCode::FORBIDDEN,
+            Self::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::ModelNotReady => StatusCode::SERVICE_UNAVAILABLE,
+            // The server is misconfigured; there's nothing the client can fix
+            Self::ModelNotFound(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::InvalidModel(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Overloaded => StatusCode::SERVICE_UNAVAILABLE,
+            Self::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            Self::ConversionFailed(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::TranscriptionFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            // nginx's "client closed request": the client called it off
+            Self::Cancelled => StatusCode::from_u16(499).unwrap_or(StatusCode::BAD_REQUEST),
+            Self::Storage(_) => StatusCode::BAD_GATEWAY,
+            Self::Queue(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Stable machine-readable code, so clients can branch without parsing messages
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound(_) => "NOT_FOUND",
+            Self::AudioNotFound => "AUDIO_NOT_FOUND",
+            Self::InvalidRequest(_) => "INVALID_REQUEST",
+            Self::Unsupported(_) => "UNSUPPORTED",
+            Self::Unauthorized(_) => "UNAUTHORIZED",
+            Self::Forbidden(_) => "FORBIDDEN",
+            Self::TooLarge(_) => "TOO_LARGE",
+            Self::ModelNotReady => "MODEL_NOT_READY",
+            Self::ModelNotFound(_) => "MODEL_NOT_FOUND",
+            Self::InvalidModel(_) => "INVALID_MODEL",
+            Self::Overloaded => "OVERLOADED",
+            Self::RateLimited(_) => "RATE_LIMITED",
+            Self::ConversionFailed(_) => "CONVERSION_FAILED",
+            Self::TranscriptionFailed(_) => "TRANSCRIPTION_FAILED",
+            Self::Timeout(_) => "TIMEOUT",
+            Self::Conflict(_) => "CONFLICT",
+            Self::Cancelled => "CANCELLED",
+            Self::Storage(_) => "STORAGE_ERROR",
+            Self::Queue(_) => "QUEUE_ERROR",
+            Self::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    /// Message safe to show clients; internal details and server paths are logged instead
+    pub fn client_message(&self) -> String {
+        match self {
+            Self::Internal(detail) => {
+                error!("Internal error: {}", detail);
+                "Internal error".to_string()
+            }
+            Self::ModelNotFound(_) | Self::InvalidModel(_) => {
+                error!("Model unavailable: {}", self);
+                "Model unavailable".to_string()
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for TranscribeError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        let retry_after = match self {
+            Self::RateLimited(secs) => Some(secs),
+            _ => None,
+        };
+
+        let body = ErrorResponse {
+            error: self.client_message(),
+            code: self.code(),
+            request_id: crate::request_id::current(),
+        };
+        let mut response = (status, Json(body)).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+impl From<s3::error::S3Error> for TranscribeError {
+    fn from(e: s3::error::S3Error) -> Self {
+        match e {
+            s3::error::S3Error::HttpFailWithBody(404, _) => Self::AudioNotFound,
+            e => Self::Storage(e.to_string()),
+        }
+    }
+}
+
+impl From<axum::extract::multipart::MultipartError> for TranscribeError {
+    fn from(e: axum::extract::multipart::MultipartError) -> Self {
+        if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            Self::TooLarge(e.body_text())
+        } else {
+            Self::InvalidRequest(e.body_text())
+        }
+    }
+}
+
+impl From<redis::RedisError> for TranscribeError {
+    fn from(e: redis::RedisError) -> Self {
+        Self::Queue(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for TranscribeError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+impl From<tokio::task::JoinError> for TranscribeError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Internal(format!("Background task failed: {}", e))
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::Path;
+use std::time::Duration;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+use crate::config::Config;
+use crate::error::{Result, TranscribeError};
+
+/// HTTP client for audio URLs, giving up on servers that don't connect or
+/// stop sending data, so a stalled URL can't hold a transcription slot
+pub fn http_client(config: &Config) -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(config.download_connect_timeout_secs))
+        .read_timeout(Duration::from_secs(config.download_read_timeout_secs))
+        .build()
+}
+
+/// Whether a file URL points at an HTTP(S) resource rather than a MinIO object
+pub fn is_http_url(file_url: &str) -> bool {
+    file_url.starts_with("http://") || file_url.starts_with("https://")
+}
+
+/// Stream an HTTP(S) resource to a local file, failing once it exceeds
+/// `max_bytes` or takes longer than `timeout`
+pub async fn download_url(
+    client: &reqwest::Client,
+    url: &str,
+    local_path: &Path,
+    max_bytes: u64,
+    timeout: Duration,
+) -> Result<()> {
+    // Signed URLs carry credentials in the query string; keep them out of logs
+    let display_url = url.split('?').next().unwrap_or(url);
+    info!("Downloading {} to {:?}", display_url, local_path);
+
+    tokio::time::timeout(
+        timeout,
+        download(client, url, display_url, local_path, max_bytes),
+    )
+    .await
+    .map_err(|_| TranscribeError::Timeout(timeout.as_secs()))?
+}
+
+async fn download(
+    client: &reqwest::Client,
+    url: &str,
+    display_url: &str,
+    local_path: &Path,
+    max_bytes: u64,
+) -> Result<()> {
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| fetch_error(display_url, e))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(TranscribeError::AudioNotFound);
+    }
+    if !status.is_success() {
+        return Err(TranscribeError::Storage(format!(
+            "Failed to fetch {}: status {}",
+            display_url, status
+        )));
+    }
+
+    if response.content_length().is_some_and(|len| len > max_bytes) {
+        return Err(too_large(max_bytes));
+    }
+
+    let mut file = File::create(local_path).await?;
+    let mut written: u64 = 0;
+
+    // Content-Length may be missing or wrong, so enforce the limit while streaming
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| fetch_error(display_url, e))?
+    {
+        written += chunk.len() as u64;
+        if written > max_bytes {
+            return Err(too_large(max_bytes));
+        }
+        file.write_all(&chunk).await?;
+    }
+
+    file.flush().await?;
+
+    info!("Downloaded {} bytes", written);
+    Ok(())
+}
+
+/// Size an HTTP(S) resource reports for itself (via `HEAD`), if it does
+pub async fn content_length(client: &reqwest::Client, url: &str) -> Result<Option<u64>> {
+    let display_url = url.split('?').next().unwrap_or(url);
+
+    let response = client
+        .head(url)
+        .send()
+        .await
+        .map_err(|e| fetch_error(display_url, e))?;
+
+    let status = response.status();
+    if status == reqwest::StatusCode::NOT_FOUND {
+        return Err(TranscribeError::AudioNotFound);
+    }
+    // Some servers refuse HEAD; leave those to ffprobe
+    if !status.is_success() {
+        return Ok(None);
+    }
+    // Read the header itself: a HEAD response has no body to size
+    Ok(response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok()))
+}
+
+fn fetch_error(display_url: &str, e: reqwest::Error) -> TranscribeError {
+    TranscribeError::Storage(format!(
+        "Failed to fetch {}: {}",
+        display_url,
+        e.without_url()
+    ))
+}
+
+fn too_large(max_bytes: u64) -> TranscribeError {
+    TranscribeError::TooLarge(format!(
+        "Audio exceeds the {} byte download limit",
+        max_bytes
+    ))
+}
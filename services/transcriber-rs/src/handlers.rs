@@ -1,58 +1,512 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
-    response::IntoResponse,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Multipart, Path, Query, State,
+    },
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     Json,
 };
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use futures::future::BoxFuture;
+use futures::{FutureExt, Stream, StreamExt};
+use metrics_exporter_prometheus::PrometheusHandle;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tempfile::NamedTempFile;
-use tokio::sync::RwLock;
-use tracing::{error, info};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::{mpsc, watch, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::AbortHandle;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{error, info, info_span, instrument, warn, Instrument, Span};
 use uuid::Uuid;
 
-use crate::queue::{JobStatus, Queue, TranscriptionStatus};
+use crate::auth::constant_time_eq;
+use crate::cache;
+use crate::callback::Callbacks;
+use crate::engine::ModelPrecision;
+use crate::error::{Result, TranscribeError};
+use crate::fetch;
+use crate::language;
+use crate::metrics;
+use crate::models::{self, ModelInfo};
+use crate::queue::{
+    CallbackDelivery, ClaimedItem, DeadLetter, JobResult, JobStatus, Queue, QueuedItem,
+    TranscriptionStatus,
+};
+use crate::rate_limit::RateLimit;
+use crate::realtime::{LiveSession, LiveUpdate};
+use crate::segments;
 use crate::storage::Storage;
-use crate::transcriber::Transcriber;
+use crate::subtitles;
+use crate::transcriber::{
+    self, AudioInfo, Clip, Probe, Profile, Segment, TranscribeOptions, Transcriber,
+    TranscriptionResult,
+};
 
 pub struct AppState {
     pub transcriber: RwLock<Transcriber>,
     pub storage: Storage,
     pub queue: Queue,
+    pub callbacks: Callbacks,
+    /// Client for audio hosted outside MinIO
+    pub http_client: reqwest::Client,
     pub bucket_name: String,
+    pub store_subtitles: bool,
+    pub segment_gap_secs: Option<f64>,
+    pub default_language: Option<String>,
+    /// Bounds how many transcriptions (and their temp files) are in progress at once
+    pub transcription_slots: Arc<Semaphore>,
+    pub permit_timeout: Duration,
+    pub max_upload_bytes: usize,
+    pub in_memory_max_bytes: usize,
+    pub max_inline_result_bytes: usize,
+    pub max_download_bytes: u64,
+    pub download_timeout: Duration,
+    pub max_multi_files: usize,
+    pub max_batch_size: usize,
+    /// How long `POST /transcribe/batch?wait=true` runs before giving up
+    pub batch_wait_timeout: Duration,
+    /// How long cached results live; 0 disables the cache
+    pub cache_ttl_secs: u64,
+    pub idempotency_ttl_secs: u64,
+    pub filler_words: HashMap<String, Vec<String>>,
+    pub merge_gap_secs: f64,
+    pub silence_threshold_db: f32,
+    pub punctuation_pause_secs: f64,
+    pub normalize_text: bool,
+    pub unspaced_languages: Vec<String>,
+    pub language_detect_secs: f64,
+    pub result_url_expiry_secs: u32,
+    pub models_dir: PathBuf,
+    /// Where downloaded and uploaded audio is written
+    pub temp_dir: PathBuf,
+    pub metrics: PrometheusHandle,
+    /// Token guarding admin endpoints; `None` disables them
+    pub admin_token: Option<String>,
+    /// Key required on every non-probe route; `None` leaves the API open
+    pub api_key: Option<String>,
+    pub rate_limit: Option<RateLimit>,
+    pub started_at: Instant,
+    pub activity: metrics::Activity,
+    /// Abort handles for in-flight sync transcriptions, keyed by request id
+    pub in_flight: Mutex<HashMap<String, AbortHandle>>,
+    /// Transcriptions in progress, by `shared_key`, so concurrent requests
+    /// for the same recording wait for the first instead of repeating it
+    pub in_progress: Mutex<HashMap<String, watch::Receiver<Option<TranscriptionResult>>>>,
+    /// The last deep health check and when it ran; also serializes them
+    pub deep_health: tokio::sync::Mutex<Option<(Instant, InferenceCheck)>>,
+}
+
+/// Removes an in-flight entry (and aborts its task) when the request finishes
+/// or the client goes away.
+struct InFlightGuard {
+    state: Arc<AppState>,
+    request_id: String,
+    task_id: tokio::task::Id,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut in_flight = self.state.in_flight.lock().unwrap();
+        // The entry may already belong to a newer request reusing the same id
+        if in_flight.get(&self.request_id).map(|h| h.id()) == Some(self.task_id) {
+            if let Some(handle) = in_flight.remove(&self.request_id) {
+                handle.abort();
+            }
+        }
+    }
+}
+
+/// The slot of a transcription that can be cancelled. Cancelling aborts the
+/// task, but inference already running on the blocking pool can't be
+/// stopped; a slot dropped before `finish` stays taken until the engine the
+/// transcription used is free, so the next request doesn't pile onto it.
+struct CancellableSlot {
+    permit: Option<OwnedSemaphorePermit>,
+    engine_idle: Option<BoxFuture<'static, ()>>,
+}
+
+impl CancellableSlot {
+    async fn new(state: &AppState, permit: OwnedSemaphorePermit) -> Self {
+        Self {
+            permit: Some(permit),
+            engine_idle: Some(state.transcriber.read().await.engine_idle().boxed()),
+        }
+    }
+
+    /// Release the slot now: the transcription ran to completion
+    fn finish(mut self) {
+        self.permit = None;
+    }
+}
+
+impl Drop for CancellableSlot {
+    fn drop(&mut self) {
+        if let (Some(permit), Some(engine_idle)) = (self.permit.take(), self.engine_idle.take()) {
+            tokio::spawn(async move {
+                engine_idle.await;
+                drop(permit);
+            });
+        }
+    }
+}
+
+/// Removes an in-progress entry when its transcription finishes, fails or is
+/// dropped, so requests waiting on it see the outcome
+struct InProgressGuard<'a> {
+    state: &'a AppState,
+    key: &'a str,
+}
+
+impl Drop for InProgressGuard<'_> {
+    fn drop(&mut self) {
+        self.state.in_progress.lock().unwrap().remove(self.key);
+    }
 }
 
 // Request/Response types
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TranscribeRequest {
+    /// MinIO object key, `s3://` URL, or HTTP(S) URL
     pub file_url: String,
+    /// Version of the MinIO object to transcribe (in a versioned bucket)
+    /// instead of the latest
+    pub version_id: Option<String>,
     pub recording_id: String,
     pub callback_url: Option<String>,
+    /// Client-chosen id for cancelling a sync transcription (generated if absent)
+    pub request_id: Option<String>,
+    /// Silence gap (seconds) that starts a new segment, overriding the config default
+    pub segment_gap_secs: Option<f64>,
+    /// ISO 639-1 language hint, falling back to the configured default
+    pub language: Option<String>,
+    /// Label segments with speakers (requires diarization to be enabled)
+    #[serde(default)]
+    pub diarize: bool,
+    /// Re-run inference even if a cached result exists for this audio
+    #[serde(default)]
+    pub skip_cache: bool,
+    /// Batch priority (0-255, higher first); equal priorities run in submission order
+    #[serde(default)]
+    pub priority: u8,
+    /// Bucket key to also write the full result to as JSON
+    pub store_result_key: Option<String>,
+    /// Transcribe only from this many seconds into the file; timestamps stay
+    /// relative to the whole file
+    pub clip_start: Option<f64>,
+    /// Transcribe only up to this many seconds into the file
+    pub clip_end: Option<f64>,
+    /// Granularity of the returned segments (streams always send words)
+    #[serde(default)]
+    pub timestamp_mode: TimestampMode,
+    /// Strip the language's filler words ("um", "uh") from the returned text
+    #[serde(default)]
+    pub remove_fillers: bool,
+    /// Join adjacent segments separated by less than the configured gap
+    #[serde(default)]
+    pub merge_gaps: bool,
+    /// Drop words spoken over near-silence, which the model tends to hallucinate
+    #[serde(default)]
+    pub drop_silence_hallucinations: bool,
+    /// Add sentence punctuation and capitalization at pauses; off returns the
+    /// model's output as is
+    #[serde(default)]
+    pub restore_punctuation: bool,
+    /// Identify the spoken language from the start of the transcript, instead
+    /// of reporting the `language` hint; short clips keep the hint
+    #[serde(default)]
+    pub auto_detect_language: bool,
+    /// Replays the first response for repeats of the same key (the
+    /// `Idempotency-Key` header takes precedence)
+    pub idempotency_key: Option<String>,
+    /// Caller's own data (meeting id, user id, ...), echoed back verbatim in
+    /// the response, the stored result and the callback
+    pub metadata: Option<serde_json::Value>,
+    /// `fast` or `accurate`; the configured defaults when unset
+    pub profile: Option<Profile>,
 }
 
-#[derive(Debug, Serialize)]
+/// How the words of a transcript are returned as segments. Inference always
+/// produces words; the other modes are derived from them, so cached results
+/// serve every mode.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampMode {
+    /// Words, grouped by `segment_gap_secs` when set
+    #[default]
+    Words,
+    /// Sentences, split on terminal punctuation and long pauses
+    Segments,
+    /// Text only, no segments
+    None,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Srt,
+    Vtt,
+    /// One JSON object per segment per line (`application/x-ndjson`)
+    Jsonl,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TranscribeQuery {
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// Overrides the request's `timestamp_mode`
+    pub granularity: Option<Granularity>,
+}
+
+/// Segment granularity selectable in the query string
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Word,
+    Sentence,
+}
+
+impl From<Granularity> for TimestampMode {
+    fn from(granularity: Granularity) -> Self {
+        match granularity {
+            Granularity::Word => Self::Words,
+            Granularity::Sentence => Self::Segments,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TranscribeResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
     pub recording_id: String,
     pub text: String,
     pub segments: Vec<SegmentResponse>,
     pub duration: f64,
+    pub language: Option<String>,
+    /// The request's `metadata`, unchanged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    /// How sure `auto_detect_language` is of `language` (0-1)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language_confidence: Option<f32>,
+    /// Untransformed text, when `remove_fillers`,
+    /// `drop_silence_hallucinations` or `restore_punctuation` changed `text`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_text: Option<String>,
+    /// The transcript stopped early and covers only part of the audio
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    /// Sample rate, channels, codec and length of the source audio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_info: Option<AudioInfo>,
+    /// Profile the audio was transcribed with, when one was requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
+    /// Wall-clock time for download, conversion and inference together
+    pub processing_time_ms: u64,
+    pub download_ms: u64,
+    pub convert_ms: u64,
+    pub inference_ms: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SegmentResponse {
     pub start: f64,
     pub end: f64,
     pub text: String,
+    /// `None` when the engine reports no confidence (distinct from zero)
+    pub confidence: Option<f32>,
+    /// Speaker label when diarization was requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speaker: Option<String>,
+    /// RMS level (dBFS) of the audio under the segment, when measured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level_db: Option<f32>,
+    /// Language of the segment, when the engine reports languages per word
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+}
+
+impl From<Segment> for SegmentResponse {
+    fn from(s: Segment) -> Self {
+        Self {
+            start: s.start,
+            end: s.end,
+            text: s.text,
+            confidence: s.confidence,
+            speaker: s.speaker,
+            level_db: s.level_db,
+            language: s.language,
+        }
+    }
+}
+
+/// Query parameters of a live transcription socket
+#[derive(Debug, Deserialize)]
+pub struct LiveParams {
+    /// Only `pcm_s16le` (little-endian 16-bit mono) is supported
+    #[serde(default = "default_live_encoding")]
+    pub encoding: String,
+    #[serde(default = "default_live_sample_rate")]
+    pub sample_rate: u32,
+}
+
+fn default_live_encoding() -> String {
+    "pcm_s16le".to_string()
+}
+
+fn default_live_sample_rate() -> u32 {
+    16000
+}
+
+/// Message sent over a live transcription socket
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LiveMessage {
+    Transcript {
+        is_final: bool,
+        text: String,
+        segments: Vec<SegmentResponse>,
+    },
+    Error {
+        error: String,
+        code: &'static str,
+    },
+}
+
+/// Final event of a streamed transcription
+#[derive(Debug, Serialize)]
+pub struct StreamDone {
+    pub recording_id: String,
+    pub duration: f64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
 pub struct HealthResponse {
     pub status: String,
     pub model_loaded: bool,
+    pub model_path: Option<String>,
+    /// Engine the model runs on
+    pub backend: &'static str,
+    /// `fp32` or `int8` (`auto` when the model's files weren't recognized)
+    pub model_precision: Option<&'static str>,
+    pub uptime_seconds: u64,
+    pub version: &'static str,
+    /// Transcriptions running on this instance, batch items included
+    pub transcriptions_in_flight: usize,
+    /// Batch items running on this instance
+    pub batch_items_in_flight: usize,
+    /// Batch items waiting in the work queue shared by every instance;
+    /// `null` when Redis is unreachable
+    pub queued_items: Option<u64>,
+    /// Outcome of the test inference, with `?deep=true`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inference: Option<InferenceCheck>,
+}
+
+/// Query parameters of `GET /health`
+#[derive(Debug, Default, Deserialize)]
+pub struct HealthParams {
+    /// Also transcribe a second of silence to prove inference works. Needs
+    /// the admin token and a transcription slot; the result is reused for
+    /// `DEEP_HEALTH_CACHE`.
+    #[serde(default)]
+    pub deep: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InferenceCheck {
+    pub passed: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ReloadRequest {
+    /// Model directory to load; defaults to the currently loaded one
+    pub model_path: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReloadResponse {
+    pub status: String,
+    pub model_path: String,
+}
+
+/// Outcome of one file of a `/transcribe/multi` call or a waited batch
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MultiItemResponse {
+    pub recording_id: String,
+    /// `completed` or `failed`
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<TranscribeResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+}
+
+/// Outcome of checking one file with `POST /validate`
+#[derive(Debug, Serialize)]
+pub struct ValidationResponse {
+    pub recording_id: String,
+    pub ok: bool,
+    /// Why the file would fail to transcribe
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
+    /// Container format detected by ffprobe (e.g. `ogg`, `mov,mp4,m4a,3gp,3g2,mj2`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    /// Length in seconds, when the container records it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f64>,
+}
+
+/// Query parameters of `POST /transcribe/batch`
+#[derive(Debug, Deserialize)]
+pub struct BatchParams {
+    /// Run the batch in the request and return its results instead of a job id
+    #[serde(default)]
+    pub wait: bool,
+    /// Where to POST a `JobCallback` once the whole job has completed, in
+    /// addition to each recording's own callback. Its delivery is tracked
+    /// under `/callbacks/job:{job_id}`. Not used with `wait`.
+    pub job_callback_url: Option<String>,
 }
 
+/// Body of a job's completion callback
 #[derive(Debug, Serialize)]
+pub struct JobCallback {
+    pub job_id: String,
+    pub status: JobStatus,
+    /// Recordings transcribed successfully
+    pub completed: u32,
+    /// Recordings that failed, now in the dead-letter queue
+    pub failed: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct BatchResponse {
     pub job_id: String,
     pub status: String,
@@ -62,307 +516,2215 @@ pub struct BatchResponse {
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
+    /// Stable identifier of the kind of error (`MODEL_NOT_READY`, `AUDIO_NOT_FOUND`, ...)
+    pub code: &'static str,
+    /// `X-Request-Id` of the request that failed, for correlating with logs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
-// Handlers
+/// Reject per-request options that can't be honoured
+fn validate_request(request: &TranscribeRequest) -> Result<()> {
+    if request.recording_id.trim().is_empty() {
+        return Err(TranscribeError::InvalidRequest(
+            "recording_id must not be empty".to_string(),
+        ));
+    }
+    if request.file_url.trim().is_empty() {
+        return Err(TranscribeError::InvalidRequest(format!(
+            "file_url must not be empty for recording {}",
+            request.recording_id
+        )));
+    }
 
-pub async fn health(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let transcriber = state.transcriber.read().await;
-    Json(HealthResponse {
-        status: "healthy".to_string(),
-        model_loaded: transcriber.is_ready(),
-    })
-}
+    if let Some(ref version_id) = request.version_id {
+        if version_id.trim().is_empty() {
+            return Err(TranscribeError::InvalidRequest(format!(
+                "version_id must not be empty for recording {}",
+                request.recording_id
+            )));
+        }
+        if fetch::is_http_url(&request.file_url) {
+            return Err(TranscribeError::InvalidRequest(format!(
+                "version_id applies only to MinIO objects, not HTTP(S) URLs, for recording {}",
+                request.recording_id
+            )));
+        }
+    }
 
-pub async fn transcribe(
-    State(state): State<Arc<AppState>>,
-    Json(request): Json<TranscribeRequest>,
-) -> Result<Json<TranscribeResponse>, (StatusCode, Json<ErrorResponse>)> {
-    info!("Transcribe request for recording: {}", request.recording_id);
+    if let Some(gap) = request.segment_gap_secs {
+        if gap.is_nan() || gap <= 0.0 {
+            return Err(TranscribeError::InvalidRequest(format!(
+                "segment_gap_secs must be positive for recording {}",
+                request.recording_id
+            )));
+        }
+    }
 
-    // Check if model is ready
-    {
-        let transcriber = state.transcriber.read().await;
-        if !transcriber.is_ready() {
-            return Err((
-                StatusCode::SERVICE_UNAVAILABLE,
-                Json(ErrorResponse {
-                    error: "Transcriber not ready".to_string(),
-                }),
-            ));
+    if let Some(ref language) = request.language {
+        let valid =
+            (2..=3).contains(&language.len()) && language.chars().all(|c| c.is_ascii_alphabetic());
+        if !valid {
+            return Err(TranscribeError::InvalidRequest(format!(
+                "Invalid language code '{}' for recording {}",
+                language, request.recording_id
+            )));
         }
     }
 
-    // Create temp file for downloaded audio
-    let temp_file = NamedTempFile::new().map_err(|e| {
-        error!("Failed to create temp file: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Internal error".to_string(),
-            }),
-        )
-    })?;
+    for (name, value) in [
+        ("clip_start", request.clip_start),
+        ("clip_end", request.clip_end),
+    ] {
+        if value.is_some_and(|v| !v.is_finite() || v < 0.0) {
+            return Err(TranscribeError::InvalidRequest(format!(
+                "{} must be a non-negative number of seconds for recording {}",
+                name, request.recording_id
+            )));
+        }
+    }
+    if let Some(end) = request.clip_end {
+        if end <= request.clip_start.unwrap_or_default() {
+            return Err(TranscribeError::InvalidRequest(format!(
+                "clip_end must be after clip_start for recording {}",
+                request.recording_id
+            )));
+        }
+    }
 
-    let temp_path = temp_file.path().to_path_buf();
+    if request
+        .store_result_key
+        .as_ref()
+        .is_some_and(|key| key.trim().is_empty())
+    {
+        return Err(TranscribeError::InvalidRequest(format!(
+            "store_result_key must not be empty for recording {}",
+            request.recording_id
+        )));
+    }
 
-    // Download from MinIO
-    let object_key = state
-        .storage
-        .normalize_object_key(&request.file_url, &state.bucket_name);
+    Ok(())
+}
 
-    state
-        .storage
-        .download_file(object_key, &temp_path)
-        .await
-        .map_err(|e| {
-            error!("Failed to download file: {}", e);
-            (
-                StatusCode::NOT_FOUND,
-                Json(ErrorResponse {
-                    error: format!("Audio file not found: {}", e),
-                }),
-            )
-        })?;
+/// Transcriber options for a request, applying config defaults
+fn transcribe_options(state: &AppState, request: &TranscribeRequest) -> TranscribeOptions {
+    TranscribeOptions {
+        language: request
+            .language
+            .clone()
+            .or_else(|| state.default_language.clone())
+            .map(|l| l.to_lowercase()),
+        diarize: request.diarize,
+        clip: (request.clip_start.is_some() || request.clip_end.is_some()).then(|| Clip {
+            start: request.clip_start.unwrap_or_default(),
+            end: request.clip_end,
+        }),
+        profile: request.profile,
+    }
+}
 
-    // Transcribe
-    let transcriber = state.transcriber.read().await;
-    let result = transcriber.transcribe(&temp_path).await.map_err(|e| {
-        error!("Transcription failed: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Transcription failed: {}", e),
-            }),
-        )
-    })?;
+/// Apply the effective segment gap (request override, then config default)
+fn group_segments(
+    state: &AppState,
+    request: &TranscribeRequest,
+    words: Vec<Segment>,
+) -> Vec<Segment> {
+    match request.segment_gap_secs.or(state.segment_gap_secs) {
+        Some(gap) => segments::group_by_gap(&words, gap),
+        None => words,
+    }
+}
 
-    // Convert segments
-    let segments: Vec<SegmentResponse> = result
-        .segments
-        .into_iter()
-        .map(|s| SegmentResponse {
-            start: s.start,
-            end: s.end,
-            text: s.text,
-        })
-        .collect();
+/// Check the admin token from `Authorization: Bearer <token>` or `X-Admin-Token`
+fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<()> {
+    let Some(expected) = state.admin_token.as_deref() else {
+        return Err(TranscribeError::Forbidden(
+            "Admin endpoints are disabled".to_string(),
+        ));
+    };
 
-    Ok(Json(TranscribeResponse {
-        recording_id: request.recording_id,
-        text: result.text,
-        segments,
-        duration: result.duration,
-    }))
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| headers.get("x-admin-token").and_then(|v| v.to_str().ok()));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+        _ => Err(TranscribeError::Unauthorized(
+            "Missing or invalid admin token".to_string(),
+        )),
+    }
 }
 
-pub async fn transcribe_batch(
+// Handlers
+
+pub async fn health(
     State(state): State<Arc<AppState>>,
-    Json(requests): Json<Vec<TranscribeRequest>>,
-) -> Result<Json<BatchResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let job_id = Uuid::new_v4().to_string();
-    let count = requests.len();
+    headers: HeaderMap,
+    Query(params): Query<HealthParams>,
+) -> Result<(StatusCode, Json<HealthResponse>)> {
+    let inference = if params.deep {
+        // /health skips the API key and rate limit, so real inference is
+        // kept behind the admin token
+        require_admin(&state, &headers)?;
+        Some(deep_health_check(&state).await?)
+    } else {
+        None
+    };
 
-    info!("Batch transcribe job {} with {} files", job_id, count);
+    // Unhealthy: inference doesn't work. Degraded: Redis is down, so only
+    // synchronous transcription works.
+    let (code, status) = if inference.as_ref().is_some_and(|check| !check.passed) {
+        (StatusCode::SERVICE_UNAVAILABLE, "unhealthy")
+    } else if state.queue.is_available() {
+        (StatusCode::OK, "healthy")
+    } else {
+        (StatusCode::OK, "degraded")
+    };
 
-    // Initialize job status
-    state
-        .queue
-        .set_job_status(
-            &job_id,
-            &JobStatus {
-                status: "queued".to_string(),
-                current: Some(0),
-                total: Some(count as u32),
-            },
-        )
-        .await
-        .map_err(|e| {
-            error!("Failed to set job status: {}", e);
-            (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to queue job".to_string(),
-                }),
-            )
-        })?;
+    let transcriber = state.transcriber.read().await;
+    let response = HealthResponse {
+        status: status.to_string(),
+        model_loaded: transcriber.is_loaded(),
+        model_path: transcriber.model_path().map(|p| p.display().to_string()),
+        backend: transcriber.backend().as_str(),
+        model_precision: transcriber.loaded_precision().map(ModelPrecision::as_str),
+        uptime_seconds: state.started_at.elapsed().as_secs(),
+        version: env!("CARGO_PKG_VERSION"),
+        transcriptions_in_flight: state.activity.in_flight(),
+        batch_items_in_flight: state.activity.batch_in_flight(),
+        queued_items: state.queue.queue_len().await.ok(),
+        inference,
+    };
+    Ok((code, Json(response)))
+}
 
-    // Spawn background task
-    let state_clone = state.clone();
-    let job_id_clone = job_id.clone();
-    tokio::spawn(async move {
-        process_batch(state_clone, job_id_clone, requests).await;
-    });
+/// Longest a deep health check waits for the engine and its test inference
+const DEEP_HEALTH_TIMEOUT: Duration = Duration::from_secs(30);
 
-    Ok(Json(BatchResponse {
-        job_id,
-        status: "queued".to_string(),
-        count,
-    }))
+/// How long a deep health check's result is reused before inference runs again
+const DEEP_HEALTH_CACHE: Duration = Duration::from_secs(10);
+
+/// Transcribe a second of silence in a transcription slot, or reuse the
+/// result of a check that ran less than `DEEP_HEALTH_CACHE` ago
+async fn deep_health_check(state: &AppState) -> Result<InferenceCheck> {
+    let mut last = state.deep_health.lock().await;
+    if let Some((checked_at, ref check)) = *last {
+        if checked_at.elapsed() < DEEP_HEALTH_CACHE {
+            return Ok(check.clone());
+        }
+    }
+
+    let _permit = acquire_slot(state).await?;
+    let started = Instant::now();
+    let check = {
+        let transcriber = state.transcriber.read().await;
+        tokio::time::timeout(DEEP_HEALTH_TIMEOUT, transcriber.check_inference())
+            .await
+            .unwrap_or(Err(TranscribeError::Timeout(DEEP_HEALTH_TIMEOUT.as_secs())))
+    };
+    if let Err(ref e) = check {
+        warn!("Deep health check failed: {}", e);
+    }
+    let check = InferenceCheck {
+        passed: check.is_ok(),
+        latency_ms: started.elapsed().as_millis() as u64,
+        error: check.err().map(|e| e.client_message()),
+    };
+
+    *last = Some((Instant::now(), check.clone()));
+    Ok(check)
 }
 
-async fn process_batch(state: Arc<AppState>, job_id: String, requests: Vec<TranscribeRequest>) {
-    let total = requests.len();
+/// Liveness probe: succeeds as long as the HTTP server is serving
+pub async fn live() -> StatusCode {
+    StatusCode::OK
+}
 
-    for (i, request) in requests.into_iter().enumerate() {
-        // Update progress
-        let _ = state
-            .queue
-            .set_job_status(
-                &job_id,
-                &JobStatus {
-                    status: "processing".to_string(),
-                    current: Some((i + 1) as u32),
-                    total: Some(total as u32),
-                },
-            )
-            .await;
+/// Readiness probe: fails until the model has finished loading
+pub async fn ready(State(state): State<Arc<AppState>>) -> StatusCode {
+    if state.transcriber.read().await.is_ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    }
+}
 
-        // Create temp file
-        let temp_file = match NamedTempFile::new() {
-            Ok(f) => f,
-            Err(e) => {
-                error!("Failed to create temp file: {}", e);
-                let _ = state
-                    .queue
-                    .set_transcription_result(
-                        &request.recording_id,
-                        &TranscriptionStatus {
-                            status: "failed".to_string(),
-                            text: None,
-                            duration: None,
-                            error: Some(e.to_string()),
-                        },
-                    )
-                    .await;
-                continue;
-            }
-        };
+pub async fn get_metrics(State(state): State<Arc<AppState>>) -> String {
+    state.metrics.render()
+}
+
+#[instrument(skip_all, fields(request_id, recording_id = %request.recording_id))]
+pub async fn transcribe(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<TranscribeQuery>,
+    headers: HeaderMap,
+    Json(mut request): Json<TranscribeRequest>,
+) -> Result<Response> {
+    if let Some(granularity) = query.granularity {
+        request.timestamp_mode = granularity.into();
+    }
+    let request_id = request
+        .request_id
+        .clone()
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    Span::current().record("request_id", request_id.as_str());
 
-        let temp_path = temp_file.path().to_path_buf();
+    info!("Transcribe request received");
 
-        // Download
-        let object_key = state
-            .storage
-            .normalize_object_key(&request.file_url, &state.bucket_name);
+    validate_request(&request)?;
 
-        if let Err(e) = state.storage.download_file(object_key, &temp_path).await {
-            error!("Failed to download {}: {}", request.recording_id, e);
-            let _ = state
-                .queue
-                .set_transcription_result(
-                    &request.recording_id,
-                    &TranscriptionStatus {
-                        status: "failed".to_string(),
-                        text: None,
-                        duration: None,
-                        error: Some(e.to_string()),
-                    },
-                )
-                .await;
-            continue;
+    let idempotency_key = idempotency_key(&headers, request.idempotency_key.as_deref());
+    if let Some(response) =
+        claim_idempotency::<TranscribeResponse>(&state, "transcribe", idempotency_key.as_deref())
+            .await?
+    {
+        info!("Replaying response for idempotency key");
+        return Ok(render_response(response, &query.format));
+    }
+
+    let result = run_cancellable(&state, request, request_id).await;
+    finish_idempotency(&state, "transcribe", idempotency_key.as_deref(), &result).await;
+
+    Ok(render_response(result?, &query.format))
+}
+
+/// Run a transcription in a separate task so it can be aborted via
+/// DELETE /transcribe/{request_id}
+async fn run_cancellable(
+    state: &Arc<AppState>,
+    request: TranscribeRequest,
+    request_id: String,
+) -> Result<TranscribeResponse> {
+    let task = {
+        let mut in_flight = state.in_flight.lock().unwrap();
+        if in_flight.contains_key(&request_id) {
+            return Err(TranscribeError::Conflict(format!(
+                "Request {} is already in progress",
+                request_id
+            )));
         }
 
-        // Transcribe
-        let transcriber = state.transcriber.read().await;
-        match transcriber.transcribe(&temp_path).await {
-            Ok(result) => {
-                // Store result
-                let _ = state
-                    .queue
-                    .set_transcription_result(
-                        &request.recording_id,
-                        &TranscriptionStatus {
-                            status: "completed".to_string(),
-                            text: Some(result.text.clone()),
-                            duration: Some(result.duration),
-                            error: None,
-                        },
-                    )
-                    .await;
+        let task = tokio::spawn(
+            run_transcription(state.clone(), request, request_id.clone()).in_current_span(),
+        );
+        in_flight.insert(request_id.clone(), task.abort_handle());
+        task
+    };
 
-                // Send callback if provided
-                if let Some(callback_url) = request.callback_url {
-                    let segments: Vec<SegmentResponse> = result
-                        .segments
-                        .into_iter()
-                        .map(|s| SegmentResponse {
-                            start: s.start,
-                            end: s.end,
-                            text: s.text,
-                        })
-                        .collect();
-
-                    let response = TranscribeResponse {
-                        recording_id: request.recording_id.clone(),
-                        text: result.text,
-                        segments,
-                        duration: result.duration,
-                    };
-
-                    let _ = reqwest::Client::new()
-                        .post(&callback_url)
-                        .json(&response)
-                        .send()
-                        .await;
-                }
+    let _guard = InFlightGuard {
+        state: state.clone(),
+        request_id,
+        task_id: task.id(),
+    };
 
-                info!("Completed transcription for {}", request.recording_id);
-            }
-            Err(e) => {
-                error!("Transcription failed for {}: {}", request.recording_id, e);
-                let _ = state
-                    .queue
-                    .set_transcription_result(
-                        &request.recording_id,
-                        &TranscriptionStatus {
-                            status: "failed".to_string(),
-                            text: None,
-                            duration: None,
-                            error: Some(e.to_string()),
-                        },
-                    )
-                    .await;
-            }
+    match task.await {
+        Ok(result) => result,
+        Err(e) if e.is_cancelled() => {
+            info!("Transcription request was cancelled");
+            Err(TranscribeError::Cancelled)
         }
+        Err(e) => Err(e.into()),
     }
+}
 
-    // Mark job as complete
-    let _ = state
-        .queue
-        .set_job_status(
-            &job_id,
-            &JobStatus {
-                status: "completed".to_string(),
-                current: Some(total as u32),
-                total: Some(total as u32),
-            },
+fn render_response(response: TranscribeResponse, format: &OutputFormat) -> Response {
+    match format {
+        OutputFormat::Json => Json(response).into_response(),
+        OutputFormat::Srt => (
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            subtitles::to_srt(&response.segments),
         )
-        .await;
+            .into_response(),
+        OutputFormat::Vtt => (
+            [(header::CONTENT_TYPE, "text/vtt; charset=utf-8")],
+            subtitles::to_vtt(&response.segments),
+        )
+            .into_response(),
+        OutputFormat::Jsonl => (
+            [(header::CONTENT_TYPE, "application/x-ndjson")],
+            to_jsonl(&response),
+        )
+            .into_response(),
+    }
+}
 
-    info!("Batch job {} completed", job_id);
+/// A segment as a standalone JSON line, tagged with its recording
+#[derive(Serialize)]
+struct JsonlSegment<'a> {
+    recording_id: &'a str,
+    #[serde(flatten)]
+    segment: &'a SegmentResponse,
 }
 
-pub async fn get_job_status(
-    State(state): State<Arc<AppState>>,
-    Path(job_id): Path<String>,
-) -> Result<Json<JobStatus>, (StatusCode, Json<ErrorResponse>)> {
-    match state.queue.get_job_status(&job_id).await {
-        Ok(Some(status)) => Ok(Json(status)),
-        Ok(None) => Err((
-            StatusCode::NOT_FOUND,
-            Json(ErrorResponse {
-                error: "Job not found".to_string(),
-            }),
+/// Render segments as JSON lines
+fn to_jsonl(response: &TranscribeResponse) -> String {
+    let mut out = String::new();
+    for segment in &response.segments {
+        let line = JsonlSegment {
+            recording_id: &response.recording_id,
+            segment,
+        };
+        if let Ok(json) = serde_json::to_string(&line) {
+            out.push_str(&json);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Idempotency key from the `Idempotency-Key` header, else the request body
+fn idempotency_key(headers: &HeaderMap, body_key: Option<&str>) -> Option<String> {
+    headers
+        .get("idempotency-key")
+        .and_then(|v| v.to_str().ok())
+        .or(body_key)
+        .filter(|key| !key.is_empty())
+        .map(str::to_string)
+}
+
+/// Claim an idempotency key before doing the work. Returns the stored
+/// response of an earlier request with the same key, or `None` to go ahead;
+/// a duplicate arriving while the first is still running gets 409. Redis
+/// failures let the request through rather than failing it.
+async fn claim_idempotency<T: DeserializeOwned>(
+    state: &AppState,
+    scope: &str,
+    key: Option<&str>,
+) -> Result<Option<T>> {
+    let Some(key) = key else {
+        return Ok(None);
+    };
+
+    match state
+        .queue
+        .claim_idempotency_key(scope, key, state.idempotency_ttl_secs)
+        .await
+    {
+        Ok(None) => Ok(None),
+        Ok(Some(stored)) if stored.is_empty() => Err(TranscribeError::Conflict(
+            "A request with this idempotency key is still in progress".to_string(),
         )),
+        Ok(Some(stored)) => serde_json::from_str(&stored)
+            .map(Some)
+            .map_err(|e| TranscribeError::Internal(format!("Corrupt idempotent response: {}", e))),
         Err(e) => {
-            error!("Failed to get job status: {}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ErrorResponse {
-                    error: "Failed to get job status".to_string(),
-                }),
-            ))
+            warn!("Idempotency check failed, processing anyway: {}", e);
+            Ok(None)
         }
     }
 }
+
+/// Store the response for replays, or release the key so a failed request
+/// can be retried
+async fn finish_idempotency<T: Serialize>(
+    state: &AppState,
+    scope: &str,
+    key: Option<&str>,
+    result: &Result<T>,
+) {
+    let Some(key) = key else {
+        return;
+    };
+
+    let outcome = match result.as_ref().map(serde_json::to_string) {
+        Ok(Ok(json)) => {
+            state
+                .queue
+                .complete_idempotency_key(scope, key, &json, state.idempotency_ttl_secs)
+                .await
+        }
+        _ => state.queue.release_idempotency_key(scope, key).await,
+    };
+    if let Err(e) = outcome {
+        warn!("Failed to record idempotency key outcome: {}", e);
+    }
+}
+
+pub async fn cancel_transcription(
+    State(state): State<Arc<AppState>>,
+    Path(request_id): Path<String>,
+) -> Result<StatusCode> {
+    let handle = state.in_flight.lock().unwrap().remove(&request_id);
+
+    match handle {
+        Some(handle) => {
+            // Dropping the task cancels the download and the wait on inference;
+            // a blocking inference call already running finishes in the background.
+            handle.abort();
+            info!("Cancelled transcription request {}", request_id);
+            Ok(StatusCode::NO_CONTENT)
+        }
+        None => Err(TranscribeError::NotFound(
+            "Transcription request not found".to_string(),
+        )),
+    }
+}
+
+async fn run_transcription(
+    state: Arc<AppState>,
+    request: TranscribeRequest,
+    request_id: String,
+) -> Result<TranscribeResponse> {
+    // Check if model is ready
+    {
+        let transcriber = state.transcriber.read().await;
+        if !transcriber.is_ready() {
+            return Err(TranscribeError::ModelNotReady);
+        }
+    }
+
+    let permit = acquire_slot(&state).await?;
+    let slot = CancellableSlot::new(&state, permit).await;
+    let _in_flight = state.activity.start("single");
+
+    let started = Instant::now();
+    let result = download_and_transcribe(&state, &request).await;
+    slot.finish();
+    metrics::record_transcription("single", result.is_ok(), started.elapsed().as_secs_f64());
+
+    let result = result.inspect_err(|e| error!("Transcription failed: {}", e))?;
+
+    Ok(build_response(
+        &state,
+        &request,
+        Some(request_id),
+        result,
+        started.elapsed(),
+    ))
+}
+
+/// Build the client-facing response, grouping segments as requested
+fn build_response(
+    state: &AppState,
+    request: &TranscribeRequest,
+    request_id: Option<String>,
+    result: TranscriptionResult,
+    processing_time: Duration,
+) -> TranscribeResponse {
+    // Post-processing works on the words, leaving the stored and cached
+    // result untouched
+    let mut words = result.segments;
+    let mut text = result.text;
+    let mut raw_text = None;
+    let mut language = result.language;
+    let mut language_confidence = None;
+    if request.auto_detect_language {
+        if let Some(detection) = language::detect(&words, state.language_detect_secs) {
+            language = Some(detection.language.to_string());
+            language_confidence = Some(detection.confidence);
+        }
+    }
+    if request.remove_fillers {
+        let fillers = filler_words(state, language.as_deref());
+        words = segments::remove_fillers(words, &fillers);
+    }
+    if request.drop_silence_hallucinations {
+        words = segments::drop_silent(words, state.silence_threshold_db);
+    }
+    if request.restore_punctuation {
+        words = segments::restore_punctuation(words, state.punctuation_pause_secs);
+    }
+    if request.remove_fillers || request.drop_silence_hallucinations || request.restore_punctuation
+    {
+        raw_text = Some(std::mem::replace(&mut text, segments::join_text(&words)));
+    }
+
+    // Convert segments
+    let mut segments = match request.timestamp_mode {
+        TimestampMode::Words => group_segments(state, request, words),
+        TimestampMode::Segments => {
+            segments::group_by_sentence(&words, state.punctuation_pause_secs)
+        }
+        TimestampMode::None => Vec::new(),
+    };
+    if request.merge_gaps {
+        segments = segments::group_by_gap(&segments, state.merge_gap_secs);
+    }
+    if state.normalize_text {
+        let unspaced = |language: Option<&str>| {
+            language.is_some_and(|language| state.unspaced_languages.iter().any(|l| l == language))
+        };
+        text = segments::normalize_spacing(&text, unspaced(language.as_deref()));
+        for segment in &mut segments {
+            // A segment's own language wins over the transcript's
+            let language = segment.language.as_deref().or(language.as_deref());
+            segment.text = segments::normalize_spacing(&segment.text, unspaced(language));
+        }
+    }
+    let segments: Vec<SegmentResponse> = segments.into_iter().map(SegmentResponse::from).collect();
+
+    TranscribeResponse {
+        request_id,
+        recording_id: request.recording_id.clone(),
+        text,
+        segments,
+        duration: result.duration,
+        language,
+        language_confidence,
+        metadata: request.metadata.clone(),
+        raw_text,
+        partial: result.partial,
+        warning: result.warning,
+        audio_info: result.audio_info,
+        profile: request.profile,
+        processing_time_ms: processing_time.as_millis() as u64,
+        download_ms: result.timings.download_ms,
+        convert_ms: result.timings.convert_ms,
+        inference_ms: result.timings.inference_ms,
+    }
+}
+
+/// Filler words for a language; every configured language's when unknown
+fn filler_words<'a>(state: &'a AppState, language: Option<&str>) -> Vec<&'a str> {
+    let lists: Vec<&Vec<String>> = match language {
+        Some(language) => state.filler_words.get(language).into_iter().collect(),
+        None => state.filler_words.values().collect(),
+    };
+    lists.into_iter().flatten().map(String::as_str).collect()
+}
+
+/// Wait for a free transcription slot, failing with 503 after the configured timeout
+async fn acquire_slot(state: &AppState) -> Result<OwnedSemaphorePermit> {
+    match tokio::time::timeout(
+        state.permit_timeout,
+        state.transcription_slots.clone().acquire_owned(),
+    )
+    .await
+    {
+        Ok(Ok(permit)) => Ok(permit),
+        Ok(Err(e)) => Err(TranscribeError::Internal(e.to_string())),
+        Err(_) => Err(TranscribeError::Overloaded),
+    }
+}
+
+/// Content types accepted for direct uploads (ffmpeg sniffs the actual format)
+fn is_supported_upload_type(content_type: &str) -> bool {
+    content_type.starts_with("audio/")
+        || content_type.starts_with("video/")
+        || content_type == "application/ogg"
+        || content_type == "application/octet-stream"
+}
+
+#[instrument(skip_all, fields(recording_id))]
+pub async fn transcribe_upload(
+    State(state): State<Arc<AppState>>,
+    mut multipart: Multipart,
+) -> Result<Json<TranscribeResponse>> {
+    // Check if model is ready
+    {
+        let transcriber = state.transcriber.read().await;
+        if !transcriber.is_ready() {
+            return Err(TranscribeError::ModelNotReady);
+        }
+    }
+
+    let mut request = TranscribeRequest::default();
+    let mut audio: Option<UploadedAudio> = None;
+
+    while let Some(mut field) = multipart.next_field().await? {
+        match field.name() {
+            Some("file") => {
+                let content_type = field
+                    .content_type()
+                    .unwrap_or("application/octet-stream")
+                    .to_string();
+                if !is_supported_upload_type(&content_type) {
+                    return Err(TranscribeError::Unsupported(format!(
+                        "Unsupported content type: {}",
+                        content_type
+                    )));
+                }
+
+                // Small uploads stay in memory; larger ones are streamed to
+                // disk once they outgrow it, enforcing the size limit as we go
+                let mut buffer = Vec::new();
+                let mut spilled: Option<(NamedTempFile, tokio::fs::File)> = None;
+                let mut size = 0;
+
+                while let Some(chunk) = field.chunk().await? {
+                    size += chunk.len();
+                    if size > state.max_upload_bytes {
+                        return Err(TranscribeError::TooLarge(format!(
+                            "Upload exceeds {} bytes",
+                            state.max_upload_bytes
+                        )));
+                    }
+                    match spilled {
+                        Some((_, ref mut file)) => file.write_all(&chunk).await?,
+                        None if size <= state.in_memory_max_bytes => {
+                            buffer.extend_from_slice(&chunk)
+                        }
+                        None => {
+                            let temp_file = NamedTempFile::new_in(&state.temp_dir)?;
+                            let mut file = tokio::fs::File::create(temp_file.path()).await?;
+                            file.write_all(&std::mem::take(&mut buffer)).await?;
+                            file.write_all(&chunk).await?;
+                            spilled = Some((temp_file, file));
+                        }
+                    }
+                }
+
+                audio = Some(match spilled {
+                    Some((temp_file, mut file)) => {
+                        file.flush().await?;
+                        UploadedAudio::File(temp_file)
+                    }
+                    None => UploadedAudio::Memory(buffer),
+                });
+            }
+            Some(name) => {
+                let name = name.to_string();
+                let value = field.text().await?;
+                apply_form_field(&mut request, &name, &value)?;
+            }
+            None => {
+                return Err(TranscribeError::InvalidRequest(
+                    "Form field without a name".to_string(),
+                ))
+            }
+        }
+    }
+
+    let audio =
+        audio.ok_or_else(|| TranscribeError::InvalidRequest("Missing 'file' field".to_string()))?;
+
+    if request.recording_id.is_empty() {
+        request.recording_id = Uuid::new_v4().to_string();
+    }
+    validate_request(&request)?;
+    Span::current().record("recording_id", request.recording_id.as_str());
+
+    info!("Transcribe upload received");
+
+    let _permit = acquire_slot(&state).await?;
+    let _in_flight = state.activity.start("single");
+
+    let started = Instant::now();
+    let input = match audio {
+        UploadedAudio::File(ref temp_file) => AudioInput::File(temp_file.path()),
+        UploadedAudio::Memory(ref bytes) => AudioInput::Memory(bytes),
+    };
+    let result = transcribe_cached(&state, input, &request).await;
+    metrics::record_transcription("single", result.is_ok(), started.elapsed().as_secs_f64());
+
+    let result = result.inspect_err(|e| error!("Transcription failed: {}", e))?;
+
+    Ok(Json(build_response(
+        &state,
+        &request,
+        request.request_id.clone(),
+        result,
+        started.elapsed(),
+    )))
+}
+
+/// Request fields an upload can't set: its audio is the `file` part
+const UPLOAD_EXCLUDED_FIELDS: &[&str] = &["file_url", "version_id"];
+
+/// Set the `TranscribeRequest` field a form field names, using the request's
+/// own serde names and types, so uploads accept what `POST /transcribe` does.
+/// The value is read as JSON (numbers, booleans, `metadata` objects) and
+/// otherwise as text.
+fn apply_form_field(request: &mut TranscribeRequest, name: &str, value: &str) -> Result<()> {
+    let Ok(serde_json::Value::Object(mut fields)) = serde_json::to_value(&*request) else {
+        return Err(TranscribeError::Internal(
+            "Failed to encode request".to_string(),
+        ));
+    };
+    if !fields.contains_key(name) || UPLOAD_EXCLUDED_FIELDS.contains(&name) {
+        return Err(TranscribeError::InvalidRequest(format!(
+            "Unknown form field: {}",
+            name
+        )));
+    }
+
+    let candidates = serde_json::from_str(value.trim()).ok().into_iter().chain([
+        serde_json::Value::String(value.to_string()),
+        serde_json::Value::String(value.trim().to_string()),
+    ]);
+    for candidate in candidates {
+        fields.insert(name.to_string(), candidate);
+        if let Ok(updated) = serde_json::from_value(serde_json::Value::Object(fields.clone())) {
+            *request = updated;
+            return Ok(());
+        }
+    }
+
+    Err(TranscribeError::InvalidRequest(format!(
+        "Invalid {}: {}",
+        name, value
+    )))
+}
+
+/// Fetch a request's audio into a temp file: HTTP(S) URLs are downloaded
+/// directly, while `s3://` URLs and bare keys go through MinIO.
+async fn fetch_audio(
+    state: &AppState,
+    file_url: &str,
+    version_id: Option<&str>,
+) -> Result<NamedTempFile> {
+    // Create temp file for downloaded audio
+    let temp_file = NamedTempFile::new_in(&state.temp_dir)?;
+
+    let started = Instant::now();
+    if fetch::is_http_url(file_url) {
+        fetch::download_url(
+            &state.http_client,
+            file_url,
+            temp_file.path(),
+            state.max_download_bytes,
+            state.download_timeout,
+        )
+        .await?;
+    } else {
+        // Download from MinIO
+        state
+            .storage
+            .download_file(object_key(state, file_url), version_id, temp_file.path())
+            .await?;
+    }
+    metrics::record_download(started.elapsed().as_secs_f64());
+
+    Ok(temp_file)
+}
+
+/// Object key in our bucket for a file URL that isn't HTTP(S)
+fn object_key<'a>(state: &AppState, file_url: &'a str) -> &'a str {
+    let file_url = file_url.strip_prefix("s3://").unwrap_or(file_url);
+    state
+        .storage
+        .normalize_object_key(file_url, &state.bucket_name)
+}
+
+/// Download and transcribe a request's audio, or wait for an identical
+/// transcription already in progress. If that one fails (or is cancelled)
+/// the waiting request runs its own, so it reports its own error.
+async fn download_and_transcribe(
+    state: &AppState,
+    request: &TranscribeRequest,
+) -> Result<TranscriptionResult> {
+    let key = shared_key(state, request);
+
+    loop {
+        let in_progress = {
+            let mut in_progress = state.in_progress.lock().unwrap();
+            match in_progress.get(&key) {
+                Some(receiver) => Err(receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    in_progress.insert(key.clone(), receiver);
+                    Ok(sender)
+                }
+            }
+        };
+
+        match in_progress {
+            Ok(sender) => {
+                // Dropped before the sender, so nobody starts waiting on a
+                // transcription that has already ended
+                let _guard = InProgressGuard { state, key: &key };
+                let result = fetch_and_transcribe(state, request).await;
+                if let Ok(ref result) = result {
+                    sender.send_replace(Some(result.clone()));
+                }
+                return result;
+            }
+            Err(mut receiver) => {
+                info!("Waiting for the same recording's transcription already in progress");
+                let shared = receiver
+                    .wait_for(Option::is_some)
+                    .await
+                    .ok()
+                    .and_then(|result| result.clone());
+                if let Some(mut result) = shared {
+                    result.language = transcribe_options(state, request).language;
+                    store_result(state, request, &result).await?;
+                    return Ok(result);
+                }
+            }
+        }
+    }
+}
+
+/// Requests with the same key would produce the same transcription
+fn shared_key(state: &AppState, request: &TranscribeRequest) -> String {
+    let options = transcribe_options(state, request);
+    format!(
+        "{}|{}{}{}{}",
+        request.recording_id,
+        request.file_url,
+        request
+            .version_id
+            .as_deref()
+            .map(|version_id| format!("?versionId={}", version_id))
+            .unwrap_or_default(),
+        cache::options_key(&options),
+        if request.skip_cache { ":uncached" } else { "" }
+    )
+}
+
+/// Download a request's audio to a temp file and run it through the model
+async fn fetch_and_transcribe(
+    state: &AppState,
+    request: &TranscribeRequest,
+) -> Result<TranscriptionResult> {
+    let started = Instant::now();
+    let temp_file = fetch_audio(state, &request.file_url, request.version_id.as_deref())
+        .instrument(info_span!("download", recording_id = %request.recording_id))
+        .await?;
+    let download_ms = started.elapsed().as_millis() as u64;
+
+    let mut result = transcribe_cached(state, AudioInput::File(temp_file.path()), request).await?;
+    result.timings.download_ms = download_ms;
+    Ok(result)
+}
+
+/// An upload, held in memory when it is small enough
+enum UploadedAudio {
+    File(NamedTempFile),
+    Memory(Vec<u8>),
+}
+
+/// Audio to transcribe: a local file, or a small upload held in memory
+#[derive(Clone, Copy)]
+enum AudioInput<'a> {
+    File(&'a std::path::Path),
+    Memory(&'a [u8]),
+}
+
+/// Transcribe local audio, reusing a cached result for identical audio unless
+/// the request opts out. Cache failures are logged and never fail the request.
+async fn transcribe_cached(
+    state: &AppState,
+    audio: AudioInput<'_>,
+    request: &TranscribeRequest,
+) -> Result<TranscriptionResult> {
+    let options = transcribe_options(state, request);
+
+    let cache_key = if state.cache_ttl_secs > 0 {
+        let hash = match audio {
+            AudioInput::File(path) => {
+                let path = path.to_path_buf();
+                tokio::task::spawn_blocking(move || cache::content_hash(&path)).await??
+            }
+            AudioInput::Memory(bytes) => cache::bytes_hash(bytes),
+        };
+        let identity = state
+            .transcriber
+            .read()
+            .await
+            .cache_identity(options.profile);
+        Some(cache::cache_key(&hash, &identity, &options))
+    } else {
+        None
+    };
+
+    if let (Some(key), false) = (&cache_key, request.skip_cache) {
+        match state.queue.get_cached_result(key).await {
+            Ok(Some(mut result)) => {
+                info!("Using cached transcription for {}", request.recording_id);
+                result.language = options.language;
+                store_result(state, request, &result).await?;
+                return Ok(result);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read transcription cache: {}", e),
+        }
+    }
+
+    let result = {
+        let transcriber = state.transcriber.read().await;
+        // Anything but WAV already in the model's format needs ffmpeg, which
+        // reads from a file
+        let transcription = match audio {
+            AudioInput::Memory(bytes) if transcriber.decodes_in_memory(bytes, &options) => {
+                transcriber
+                    .transcribe_wav_bytes(bytes.to_vec(), &options)
+                    .await
+            }
+            AudioInput::Memory(bytes) => {
+                let spilled = NamedTempFile::new_in(&state.temp_dir)?;
+                tokio::fs::write(spilled.path(), bytes).await?;
+                transcriber.transcribe(spilled.path(), &options).await
+            }
+            AudioInput::File(path) => transcriber.transcribe(path, &options).await,
+        };
+        transcription.inspect_err(|e| {
+            if let TranscribeError::Timeout(_) = e {
+                warn!("Transcription timed out for {}", request.recording_id);
+            }
+        })?
+    };
+
+    // A partial result may succeed in full next time, so don't pin it
+    if let (Some(key), false) = (cache_key, result.partial) {
+        if let Err(e) = state
+            .queue
+            .set_cached_result(&key, &result, state.cache_ttl_secs)
+            .await
+        {
+            warn!("Failed to write transcription cache: {}", e);
+        }
+    }
+
+    store_result(state, request, &result).await?;
+    Ok(result)
+}
+
+/// Write the full result as JSON to the request's `store_result_key`, if set
+async fn store_result(
+    state: &AppState,
+    request: &TranscribeRequest,
+    result: &TranscriptionResult,
+) -> Result<()> {
+    let Some(ref key) = request.store_result_key else {
+        return Ok(());
+    };
+
+    let json = serde_json::to_vec(result).map_err(|e| TranscribeError::Internal(e.to_string()))?;
+    let object_key = state.storage.normalize_object_key(key, &state.bucket_name);
+    state
+        .storage
+        .upload_bytes(object_key, &json, "application/json")
+        .await?;
+
+    // The artifact is written either way; only the download-url lookup is lost
+    if let Err(e) = state
+        .queue
+        .set_result_artifact(&request.recording_id, object_key)
+        .await
+    {
+        warn!("Failed to record result location: {}", e);
+    }
+    Ok(())
+}
+
+#[instrument(skip_all, fields(recording_id = %request.recording_id))]
+pub async fn transcribe_stream(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<TranscribeRequest>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    info!("Streaming transcribe request received");
+
+    validate_request(&request)?;
+
+    // Check if model is ready
+    {
+        let transcriber = state.transcriber.read().await;
+        if !transcriber.is_ready() {
+            return Err(TranscribeError::ModelNotReady);
+        }
+    }
+
+    let permit = acquire_slot(&state).await?;
+
+    // Download up front so a missing file is reported as a regular HTTP error
+    let temp_file = fetch_audio(&state, &request.file_url, request.version_id.as_deref())
+        .instrument(info_span!("download", recording_id = %request.recording_id))
+        .await?;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    // The task outlives this request's scope, so take the id along
+    let request_id = crate::request_id::current();
+
+    let task = async move {
+        let _permit = permit;
+        let _in_flight = state.activity.start("stream");
+        let transcriber = state.transcriber.read().await;
+        let options = transcribe_options(&state, &request);
+        let segment_tx = tx.clone();
+        let started = Instant::now();
+
+        let transcription =
+            transcriber.transcribe_with_callback(temp_file.path(), &options, |segment| {
+                let segment = SegmentResponse::from(segment.clone());
+                if let Ok(event) = Event::default().event("segment").json_data(&segment) {
+                    let _ = segment_tx.send(event);
+                }
+            });
+        // Nobody is left to read the result once the client disconnects;
+        // stop and give the slot back rather than finishing the file
+        let result = tokio::select! {
+            result = transcription => result,
+            () = tx.closed() => {
+                info!("Client disconnected, abandoning streaming transcription");
+                return;
+            }
+        };
+        let result = match result {
+            Ok(result) => store_result(&state, &request, &result)
+                .await
+                .map(|()| result),
+            Err(e) => Err(e),
+        };
+        metrics::record_transcription("stream", result.is_ok(), started.elapsed().as_secs_f64());
+
+        let event = match result {
+            Ok(result) => Event::default().event("done").json_data(StreamDone {
+                recording_id: request.recording_id,
+                duration: result.duration,
+                partial: result.partial,
+                warning: result.warning,
+            }),
+            Err(e) => {
+                error!("Transcription failed: {}", e);
+                Event::default().event("error").json_data(ErrorResponse {
+                    error: e.client_message(),
+                    code: e.code(),
+                    request_id,
+                })
+            }
+        };
+
+        if let Ok(event) = event {
+            let _ = tx.send(event);
+        }
+    };
+    tokio::spawn(task.in_current_span());
+
+    let stream = UnboundedReceiverStream::new(rx).map(Ok);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Transcribe live audio sent as binary WebSocket frames. Partial results
+/// (`is_final: false`) are revised as more audio arrives; final results are
+/// not. The client sends the text message `end` (or closes) to flush.
+pub async fn live_transcribe(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<LiveParams>,
+    ws: WebSocketUpgrade,
+) -> Result<Response> {
+    if params.encoding != "pcm_s16le" {
+        return Err(TranscribeError::Unsupported(format!(
+            "encoding {} (only pcm_s16le is supported)",
+            params.encoding
+        )));
+    }
+    if !(8000..=48000).contains(&params.sample_rate) {
+        return Err(TranscribeError::InvalidRequest(format!(
+            "sample_rate must be between 8000 and 48000, got {}",
+            params.sample_rate
+        )));
+    }
+
+    {
+        let transcriber = state.transcriber.read().await;
+        if !transcriber.is_ready() {
+            return Err(TranscribeError::ModelNotReady);
+        }
+    }
+
+    let session = LiveSession::new(params.sample_rate);
+    Ok(ws.on_upgrade(move |socket| run_live_session(state, socket, session)))
+}
+
+async fn run_live_session(state: Arc<AppState>, mut socket: WebSocket, mut session: LiveSession) {
+    let _in_flight = state.activity.start("live");
+    info!("Live transcription session started");
+
+    while let Some(message) = socket.recv().await {
+        let finish = match message {
+            Ok(Message::Binary(frame)) => {
+                session.push_pcm(&frame);
+                false
+            }
+            Ok(Message::Text(text)) if text.trim() == "end" => true,
+            Ok(Message::Close(_)) => true,
+            Ok(_) => continue,
+            Err(e) => {
+                warn!("Live transcription socket failed: {}", e);
+                return;
+            }
+        };
+
+        if session.is_due() || (finish && session.has_undecoded()) {
+            let sent = match decode_live(&state, &mut session, finish).await {
+                Ok(update) => send_live_update(&mut socket, &state, update).await,
+                Err(e) => {
+                    error!("Live transcription failed: {}", e);
+                    send_live(
+                        &mut socket,
+                        &LiveMessage::Error {
+                            error: e.client_message(),
+                            code: e.code(),
+                        },
+                    )
+                    .await
+                }
+            };
+            if !sent {
+                return;
+            }
+        }
+
+        if finish {
+            break;
+        }
+    }
+
+    let _ = socket.send(Message::Close(None)).await;
+    info!("Live transcription session ended");
+}
+
+/// Decode the session's buffered audio, holding a transcription slot only
+/// for the duration of the decode so long sessions don't starve other work
+async fn decode_live(
+    state: &AppState,
+    session: &mut LiveSession,
+    finish: bool,
+) -> Result<LiveUpdate> {
+    let _permit = acquire_slot(state).await?;
+    let samples = session.take_window();
+    let started = Instant::now();
+
+    let transcriber = state.transcriber.read().await;
+    let result = transcriber
+        .transcribe_samples(samples, session.sample_rate())
+        .await;
+    metrics::record_transcription("live", result.is_ok(), started.elapsed().as_secs_f64());
+
+    Ok(session.apply(result?, finish))
+}
+
+/// Send the finalised words, then the current partial words; false once the
+/// client is gone
+async fn send_live_update(socket: &mut WebSocket, state: &AppState, update: LiveUpdate) -> bool {
+    for (is_final, words) in [(true, update.finals), (false, update.partials)] {
+        if words.is_empty() {
+            continue;
+        }
+        let words = match state.segment_gap_secs {
+            Some(gap) => segments::group_by_gap(&words, gap),
+            None => words,
+        };
+        let message = LiveMessage::Transcript {
+            is_final,
+            text: segments::join_text(&words),
+            segments: words.into_iter().map(SegmentResponse::from).collect(),
+        };
+        if !send_live(socket, &message).await {
+            return false;
+        }
+    }
+    true
+}
+
+async fn send_live(socket: &mut WebSocket, message: &LiveMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(json) => socket.send(Message::Text(json.into())).await.is_ok(),
+        Err(e) => {
+            error!("Failed to serialize live message: {}", e);
+            false
+        }
+    }
+}
+
+/// Transcribe a handful of files in one synchronous call. Files run one after
+/// another in a single transcription slot; a failing file is reported in its
+/// own entry instead of failing the call.
+pub async fn transcribe_multi(
+    State(state): State<Arc<AppState>>,
+    Json(requests): Json<Vec<TranscribeRequest>>,
+) -> Result<Json<Vec<MultiItemResponse>>> {
+    if requests.len() > state.max_multi_files {
+        return Err(TranscribeError::InvalidRequest(format!(
+            "At most {} files per call, got {}",
+            state.max_multi_files,
+            requests.len()
+        )));
+    }
+
+    // Check if model is ready
+    {
+        let transcriber = state.transcriber.read().await;
+        if !transcriber.is_ready() {
+            return Err(TranscribeError::ModelNotReady);
+        }
+    }
+
+    info!("Multi transcribe request with {} files", requests.len());
+
+    let _permit = acquire_slot(&state).await?;
+
+    Ok(Json(
+        transcribe_sequentially(&state, requests, "single", None).await,
+    ))
+}
+
+/// Transcribe files one after another, reporting each file's outcome in its
+/// own entry. Files still unfinished after `timeout` fail with a timeout.
+async fn transcribe_sequentially(
+    state: &AppState,
+    requests: Vec<TranscribeRequest>,
+    kind: &'static str,
+    timeout: Option<Duration>,
+) -> Vec<MultiItemResponse> {
+    let deadline = timeout.map(|timeout| (Instant::now() + timeout, timeout));
+    let mut responses = Vec::with_capacity(requests.len());
+    for request in requests {
+        let _in_flight = state.activity.start(kind);
+        let started = Instant::now();
+
+        let result = match (validate_request(&request), deadline) {
+            (Err(e), _) => Err(e),
+            (Ok(()), Some((deadline, timeout))) if started >= deadline => {
+                Err(TranscribeError::Timeout(timeout.as_secs()))
+            }
+            (Ok(()), Some((deadline, timeout))) => {
+                tokio::time::timeout(deadline - started, download_and_transcribe(state, &request))
+                    .await
+                    .unwrap_or(Err(TranscribeError::Timeout(timeout.as_secs())))
+            }
+            (Ok(()), None) => download_and_transcribe(state, &request).await,
+        };
+        metrics::record_transcription(kind, result.is_ok(), started.elapsed().as_secs_f64());
+
+        responses.push(match result {
+            Ok(result) => MultiItemResponse {
+                recording_id: request.recording_id.clone(),
+                status: "completed".to_string(),
+                result: Some(build_response(
+                    state,
+                    &request,
+                    None,
+                    result,
+                    started.elapsed(),
+                )),
+                error: None,
+                code: None,
+            },
+            Err(e) => {
+                error!("Transcription failed for {}: {}", request.recording_id, e);
+                MultiItemResponse {
+                    recording_id: request.recording_id,
+                    status: "failed".to_string(),
+                    result: None,
+                    error: Some(e.client_message()),
+                    code: Some(e.code().to_string()),
+                }
+            }
+        });
+    }
+
+    responses
+}
+
+/// Queue a batch job, or with `wait=true` run it in this request and return
+/// every item's outcome once all have finished
+pub async fn transcribe_batch(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<BatchParams>,
+    headers: HeaderMap,
+    Json(requests): Json<Vec<TranscribeRequest>>,
+) -> Result<Response> {
+    // Jobs live in Redis; without it they could be neither queued nor tracked
+    state.queue.require_available()?;
+
+    if requests.len() > state.max_batch_size {
+        return Err(TranscribeError::InvalidRequest(format!(
+            "At most {} recordings per batch, got {}",
+            state.max_batch_size,
+            requests.len()
+        )));
+    }
+
+    // Nothing is queued unless every item is valid, and every problem is
+    // reported at once so a client can fix the batch in one go
+    let invalid: Vec<String> = requests
+        .iter()
+        .enumerate()
+        .filter_map(|(index, request)| match validate_request(request) {
+            Ok(()) => None,
+            Err(TranscribeError::InvalidRequest(reason)) => Some(format!("{}: {}", index, reason)),
+            Err(e) => Some(format!("{}: {}", index, e)),
+        })
+        .collect();
+    if !invalid.is_empty() {
+        return Err(TranscribeError::InvalidRequest(format!(
+            "Invalid batch items ({}): {}",
+            invalid.len(),
+            invalid.join("; ")
+        )));
+    }
+
+    let idempotency_key = idempotency_key(&headers, None);
+    if params.wait {
+        return run_batch_now(&state, requests, idempotency_key.as_deref())
+            .await
+            .map(|responses| Json(responses).into_response());
+    }
+
+    if let Some(response) =
+        claim_idempotency::<BatchResponse>(&state, "batch", idempotency_key.as_deref()).await?
+    {
+        info!("Replaying batch job for idempotency key");
+        return Ok(Json(response).into_response());
+    }
+
+    let result = start_batch(&state, requests, params.job_callback_url.as_deref()).await;
+    finish_idempotency(&state, "batch", idempotency_key.as_deref(), &result).await;
+
+    Ok(Json(result?).into_response())
+}
+
+/// Run a batch synchronously, one item at a time in a single transcription
+/// slot, until done or until the wait timeout; items not finished by then
+/// fail with a timeout
+async fn run_batch_now(
+    state: &AppState,
+    requests: Vec<TranscribeRequest>,
+    idempotency_key: Option<&str>,
+) -> Result<Vec<MultiItemResponse>> {
+    if let Some(responses) = claim_idempotency(state, "batch_wait", idempotency_key).await? {
+        info!("Replaying batch results for idempotency key");
+        return Ok(responses);
+    }
+
+    let result = async {
+        // Check if model is ready
+        if !state.transcriber.read().await.is_ready() {
+            return Err(TranscribeError::ModelNotReady);
+        }
+
+        info!(count = requests.len(), "Running batch synchronously");
+        let _permit = acquire_slot(state).await?;
+
+        let timeout = Some(state.batch_wait_timeout);
+        Ok(transcribe_sequentially(state, requests, "batch", timeout).await)
+    }
+    .await;
+    finish_idempotency(state, "batch_wait", idempotency_key, &result).await;
+
+    result
+}
+
+/// Queue a batch job's items for the batch workers
+#[instrument(skip_all, fields(job_id))]
+async fn start_batch(
+    state: &AppState,
+    requests: Vec<TranscribeRequest>,
+    job_callback_url: Option<&str>,
+) -> Result<BatchResponse> {
+    let job_id = Uuid::new_v4().to_string();
+    let count = requests.len();
+    Span::current().record("job_id", job_id.as_str());
+
+    info!(count, "Batch transcribe job created");
+
+    // No worker will ever finish an empty job, so it is complete from the start
+    let status = if count == 0 { "completed" } else { "queued" };
+
+    // Initialize job status
+    state
+        .queue
+        .set_job_status(
+            &job_id,
+            &JobStatus {
+                status: status.to_string(),
+                current: Some(0),
+                total: Some(count as u32),
+            },
+        )
+        .await
+        .inspect_err(|e| error!("Failed to set job status: {}", e))?;
+
+    if let Some(url) = job_callback_url {
+        if count == 0 {
+            let status = JobStatus {
+                status: status.to_string(),
+                current: Some(0),
+                total: Some(0),
+            };
+            schedule_job_callback(state, &job_id, url, status, 0).await;
+        } else {
+            // Stored before any item is queued, so the last worker finds it
+            state
+                .queue
+                .set_job_callback_url(&job_id, url)
+                .await
+                .inspect_err(|e| error!("Failed to set job callback URL: {}", e))?;
+        }
+    }
+
+    // Hand the items to the batch workers
+    for (index, request) in requests.into_iter().enumerate() {
+        let priority = request.priority;
+        let item = QueuedItem {
+            job_id: job_id.clone(),
+            index: index as u32,
+            total: count as u32,
+            request,
+        };
+        state
+            .queue
+            .enqueue(&item, priority)
+            .await
+            .inspect_err(|e| error!("Failed to enqueue batch item: {}", e))?;
+    }
+
+    Ok(BatchResponse {
+        job_id,
+        status: status.to_string(),
+        count,
+    })
+}
+
+/// How often a worker renews the heartbeat of the item it is processing
+const ITEM_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Silence after which an item's worker is presumed dead and it is requeued
+const ITEM_STALE_AFTER: Duration = Duration::from_secs(60);
+
+/// Pull batch items from the shared priority queue and process them, forever.
+/// Every pod runs its own workers, so a queue drains across the whole fleet.
+/// An item stays claimed with a heartbeat until it is handled, so delivery is
+/// at least once: an item in progress on a pod that dies is requeued by
+/// `run_recovery_sweep` (and may then be transcribed twice).
+pub async fn run_batch_worker(state: Arc<AppState>) {
+    // Items taken before the model is ready would fail straight into the dead-letter queue
+    while !state.transcriber.read().await.is_ready() {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+
+    loop {
+        // Idle until Redis is reachable instead of logging a failure a second
+        if !state.queue.is_available() {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            continue;
+        }
+
+        match state.queue.dequeue(Duration::from_secs(5)).await {
+            Ok(Some(claimed)) => process_claimed(&state, claimed).await,
+            Ok(None) => {}
+            Err(e) => {
+                error!("Failed to read batch queue: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+        }
+    }
+}
+
+/// Process a claimed item, renewing its heartbeat meanwhile so the recovery
+/// sweep leaves it alone, then acknowledge it
+async fn process_claimed(state: &AppState, claimed: ClaimedItem) {
+    let heartbeat = async {
+        loop {
+            tokio::time::sleep(ITEM_HEARTBEAT_INTERVAL).await;
+            if let Err(e) = state.queue.heartbeat_item(&claimed).await {
+                warn!("Failed to renew batch item heartbeat: {}", e);
+            }
+        }
+    };
+    tokio::select! {
+        _ = process_item(state, claimed.item.clone()) => {}
+        _ = heartbeat => {}
+    }
+
+    if let Err(e) = state.queue.ack_item(&claimed).await {
+        error!("Failed to acknowledge batch item: {}", e);
+    }
+}
+
+/// Requeue batch items whose worker stopped sending heartbeats (its pod
+/// crashed or was redeployed), once at startup and then periodically, so
+/// interrupted jobs finish instead of staying `processing` forever
+pub async fn run_recovery_sweep(state: Arc<AppState>) {
+    loop {
+        if state.queue.is_available() {
+            match state.queue.requeue_stale_items(ITEM_STALE_AFTER).await {
+                Ok(0) => {}
+                Ok(count) => warn!(count, "Requeued batch items abandoned by a stopped worker"),
+                Err(e) => error!("Failed to requeue stale batch items: {}", e),
+            }
+        }
+        tokio::time::sleep(ITEM_STALE_AFTER / 2).await;
+    }
+}
+
+/// Files probed at once by `POST /validate`
+const VALIDATE_CONCURRENCY: usize = 8;
+
+/// Lifetime of the presigned URL ffprobe reads an object through
+const PROBE_URL_EXPIRY_SECS: u32 = 300;
+
+/// Check that files exist and can be decoded, without transcribing them.
+/// Only the headers are read (with ffprobe), so whole batches can be checked
+/// cheaply before they are queued.
+pub async fn validate_files(
+    State(state): State<Arc<AppState>>,
+    Json(requests): Json<Vec<TranscribeRequest>>,
+) -> Result<Json<Vec<ValidationResponse>>> {
+    info!("Validate request with {} files", requests.len());
+
+    if requests.len() > state.max_batch_size {
+        return Err(TranscribeError::InvalidRequest(format!(
+            "At most {} recordings per batch, got {}",
+            state.max_batch_size,
+            requests.len()
+        )));
+    }
+
+    let responses = futures::stream::iter(requests)
+        .map(|request| {
+            let state = &state;
+            async move {
+                match probe_file(state, &request).await {
+                    Ok(probe) => ValidationResponse {
+                        recording_id: request.recording_id,
+                        ok: true,
+                        reason: None,
+                        code: None,
+                        format: Some(probe.format),
+                        duration: probe.duration,
+                    },
+                    Err(e) => ValidationResponse {
+                        recording_id: request.recording_id,
+                        ok: false,
+                        reason: Some(e.client_message()),
+                        code: Some(e.code()),
+                        format: None,
+                        duration: None,
+                    },
+                }
+            }
+        })
+        .buffered(VALIDATE_CONCURRENCY)
+        .collect()
+        .await;
+
+    Ok(Json(responses))
+}
+
+/// Reject audio of `len` bytes when it is over the download limit
+fn check_download_size(state: &AppState, len: u64) -> Result<()> {
+    if len > state.max_download_bytes {
+        return Err(TranscribeError::TooLarge(format!(
+            "Audio file is {} bytes, over the {} byte limit",
+            len, state.max_download_bytes
+        )));
+    }
+    Ok(())
+}
+
+/// Probe a request's audio where it is stored, failing for anything that
+/// would fail its transcription before inference
+async fn probe_file(state: &AppState, request: &TranscribeRequest) -> Result<Probe> {
+    validate_request(request)?;
+
+    let probe = if fetch::is_http_url(&request.file_url) {
+        // Servers that don't report a length are caught by the download itself
+        let len = fetch::content_length(&state.http_client, &request.file_url).await?;
+        if let Some(len) = len {
+            check_download_size(state, len)?;
+        }
+        transcriber::probe_audio(&request.file_url).await?
+    } else {
+        let object_key = object_key(state, &request.file_url);
+        let version_id = request.version_id.as_deref();
+        let len = state.storage.object_size(object_key, version_id).await?;
+        check_download_size(state, len)?;
+        // ffprobe reads the object over a short-lived presigned URL
+        let url = state
+            .storage
+            .presign_get(object_key, version_id, PROBE_URL_EXPIRY_SECS)
+            .await?;
+        transcriber::probe_audio(&url).await?
+    };
+
+    if let Some(duration) = probe.duration {
+        state.transcriber.read().await.check_length(duration)?;
+    }
+    Ok(probe)
+}
+
+/// Deliver due completion callbacks, retrying failed ones with backoff
+pub async fn run_callback_worker(state: Arc<AppState>) {
+    state.callbacks.run_worker(&state.queue).await
+}
+
+#[instrument(skip_all, fields(job_id = %item.job_id, recording_id = %item.request.recording_id))]
+async fn process_item(state: &AppState, item: QueuedItem) {
+    let QueuedItem {
+        job_id,
+        total,
+        request,
+        ..
+    } = item;
+
+    // Update progress. Items of a cancelled job are dropped as workers
+    // reach them; the check and the update are one atomic step, so a cancel
+    // can't land between them.
+    let status = JobStatus {
+        status: "processing".to_string(),
+        current: None,
+        total: None,
+    };
+    match state.queue.set_job_status(&job_id, &status).await {
+        Ok(true) => {}
+        Ok(false) => {
+            info!("Skipping item of cancelled job");
+            return;
+        }
+        Err(e) => error!("Failed to set job status: {}", e),
+    }
+
+    // Background jobs wait for a slot rather than failing
+    let _permit = state.transcription_slots.clone().acquire_owned().await;
+    let _in_flight = state.activity.start("batch");
+
+    let started = Instant::now();
+    let result = download_and_transcribe(state, &request).await;
+    metrics::record_transcription("batch", result.is_ok(), started.elapsed().as_secs_f64());
+    let failed = result.is_err();
+
+    match result {
+        Ok(result) => {
+            let response = build_response(state, &request, None, result, started.elapsed());
+
+            // Pre-render subtitles so downloads don't re-render on demand
+            let (srt, vtt) = if state.store_subtitles {
+                (
+                    Some(subtitles::to_srt(&response.segments)),
+                    Some(subtitles::to_vtt(&response.segments)),
+                )
+            } else {
+                (None, None)
+            };
+
+            // Store result
+            if let Err(e) = save_transcription_result(
+                state,
+                &request.recording_id,
+                TranscriptionStatus {
+                    status: "completed".to_string(),
+                    text: Some(response.text.clone()),
+                    duration: Some(response.duration),
+                    error: None,
+                    srt,
+                    vtt,
+                    segments: Some(response.segments.clone()),
+                    metadata: request.metadata.clone(),
+                    result_ref: None,
+                },
+            )
+            .await
+            {
+                error!("Failed to store transcription result: {}", e);
+            }
+            if let Err(e) = state
+                .queue
+                .add_job_result(&job_id, &request.recording_id)
+                .await
+            {
+                error!("Failed to record job result: {}", e);
+            }
+
+            // Send callback if provided
+            if let Some(ref callback_url) = request.callback_url {
+                state
+                    .callbacks
+                    .schedule(&state.queue, &request.recording_id, callback_url, &response)
+                    .await;
+            }
+
+            info!("Completed transcription");
+        }
+        Err(e) => {
+            error!("Transcription failed: {}", e);
+            if let Err(e) = save_transcription_result(
+                state,
+                &request.recording_id,
+                TranscriptionStatus {
+                    status: "failed".to_string(),
+                    text: None,
+                    duration: None,
+                    error: Some(e.client_message()),
+                    srt: None,
+                    vtt: None,
+                    segments: None,
+                    metadata: request.metadata.clone(),
+                    result_ref: None,
+                },
+            )
+            .await
+            {
+                error!("Failed to store transcription result: {}", e);
+            }
+            if let Err(e) = state
+                .queue
+                .add_job_result(&job_id, &request.recording_id)
+                .await
+            {
+                error!("Failed to record job result: {}", e);
+            }
+            if let Err(e) = state
+                .queue
+                .push_dead_letter(&DeadLetter {
+                    recording_id: request.recording_id.clone(),
+                    error: e.to_string(),
+                    failed_at: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs(),
+                    request,
+                })
+                .await
+            {
+                error!("Failed to push dead letter: {}", e);
+            }
+        }
+    }
+
+    // The worker finishing the last item marks the job complete
+    match state.queue.complete_job_item(&job_id, failed).await {
+        Ok((current, failures)) if current >= total => {
+            let status = JobStatus {
+                status: "completed".to_string(),
+                current: Some(total),
+                total: Some(total),
+            };
+            if let Err(e) = state.queue.set_job_status(&job_id, &status).await {
+                error!("Failed to set job status: {}", e);
+            }
+
+            match state.queue.get_job_callback_url(&job_id).await {
+                Ok(Some(url)) => {
+                    schedule_job_callback(state, &job_id, &url, status, failures).await
+                }
+                Ok(None) => {}
+                Err(e) => error!("Failed to read job callback URL: {}", e),
+            }
+
+            info!(failed = failures, "Batch job completed");
+        }
+        Ok(_) => {}
+        Err(e) => error!("Failed to update job progress: {}", e),
+    }
+}
+
+/// Queue a job's completion callback, tracked like a recording's under
+/// `job:{job_id}`
+async fn schedule_job_callback(
+    state: &AppState,
+    job_id: &str,
+    url: &str,
+    status: JobStatus,
+    failed: u32,
+) {
+    let payload = JobCallback {
+        job_id: job_id.to_string(),
+        completed: status.total.unwrap_or_default().saturating_sub(failed),
+        failed,
+        status,
+    };
+    state
+        .callbacks
+        .schedule(&state.queue, &format!("job:{}", job_id), url, &payload)
+        .await;
+}
+
+/// Bucket prefix of results too large to keep in Redis
+const SPILLED_RESULTS_PREFIX: &str = "transcriber/results";
+
+/// Store a batch result in Redis or, when its JSON is over
+/// `max_inline_result_bytes`, in the bucket with only a reference in Redis.
+/// The object outlives the reference; the bucket's lifecycle rules should
+/// clean it up.
+async fn save_transcription_result(
+    state: &AppState,
+    recording_id: &str,
+    result: TranscriptionStatus,
+) -> Result<()> {
+    let json = serde_json::to_vec(&result).map_err(|e| TranscribeError::Internal(e.to_string()))?;
+    if json.len() <= state.max_inline_result_bytes {
+        return state
+            .queue
+            .set_transcription_result(recording_id, &result)
+            .await;
+    }
+
+    let object_key = format!("{}/{}.json", SPILLED_RESULTS_PREFIX, recording_id);
+    state
+        .storage
+        .upload_bytes(&object_key, &json, "application/json")
+        .await?;
+    info!(
+        bytes = json.len(),
+        "Result too large for Redis; stored in {}", object_key
+    );
+
+    let reference = TranscriptionStatus {
+        status: result.status,
+        text: None,
+        duration: result.duration,
+        error: result.error,
+        srt: None,
+        vtt: None,
+        segments: None,
+        metadata: None,
+        result_ref: Some(object_key),
+    };
+    state
+        .queue
+        .set_transcription_result(recording_id, &reference)
+        .await
+}
+
+/// A recording's stored result, read from the bucket when it was spilled there
+async fn load_transcription_result(
+    state: &AppState,
+    recording_id: &str,
+) -> Result<Option<TranscriptionStatus>> {
+    let Some(mut result) = state.queue.get_transcription_result(recording_id).await? else {
+        return Ok(None);
+    };
+    resolve_spilled_result(state, &mut result).await?;
+    Ok(Some(result))
+}
+
+/// Replace a reference to a spilled result with the full result
+async fn resolve_spilled_result(state: &AppState, result: &mut TranscriptionStatus) -> Result<()> {
+    let Some(object_key) = result.result_ref.take() else {
+        return Ok(());
+    };
+
+    let json = state
+        .storage
+        .download_bytes(&object_key)
+        .await
+        .map_err(|e| match e {
+            TranscribeError::AudioNotFound => {
+                TranscribeError::NotFound(format!("Stored result {} no longer exists", object_key))
+            }
+            e => e,
+        })?;
+    *result = serde_json::from_slice(&json).map_err(|e| {
+        TranscribeError::Internal(format!("Unreadable stored result {}: {}", object_key, e))
+    })?;
+    Ok(())
+}
+
+/// Load a model and swap it in without restarting; transcriptions already
+/// running finish on the previous model.
+pub async fn reload_model(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    body: Option<Json<ReloadRequest>>,
+) -> Result<Json<ReloadResponse>> {
+    require_admin(&state, &headers)?;
+
+    let transcriber = state.transcriber.read().await;
+    let model_path = body
+        .and_then(|Json(request)| request.model_path)
+        .map(PathBuf::from)
+        .or_else(|| transcriber.model_path())
+        .ok_or_else(|| TranscribeError::InvalidRequest("model_path is required".to_string()))?;
+
+    transcriber.reload_model(&model_path).await?;
+
+    Ok(Json(ReloadResponse {
+        status: "reloaded".to_string(),
+        model_path: model_path.display().to_string(),
+    }))
+}
+
+/// Models installed under the models directory, flagging the active one
+pub async fn list_models(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<Vec<ModelInfo>>> {
+    require_admin(&state, &headers)?;
+
+    let active = state.transcriber.read().await.model_path();
+    let models_dir = state.models_dir.clone();
+    let models = tokio::task::spawn_blocking({
+        let models_dir = models_dir.clone();
+        move || models::discover(&models_dir, active.as_deref())
+    })
+    .await?
+    .map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => TranscribeError::NotFound(format!(
+            "Models directory {} not found",
+            models_dir.display()
+        )),
+        _ => e.into(),
+    })?;
+
+    Ok(Json(models))
+}
+
+pub async fn get_job_status(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<JobStatus>> {
+    match state.queue.get_job_status(&job_id).await {
+        Ok(Some(status)) => Ok(Json(status)),
+        Ok(None) => Err(TranscribeError::NotFound("Job not found".to_string())),
+        Err(e) => {
+            error!("Failed to get job status: {}", e);
+            Err(e)
+        }
+    }
+}
+
+/// Stop a batch job: queued items are skipped, while items already being
+/// transcribed finish and keep their results
+#[instrument(skip_all, fields(job_id = %job_id))]
+pub async fn cancel_job(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> Result<StatusCode> {
+    let status = state
+        .queue
+        .get_job_status(&job_id)
+        .await?
+        .ok_or_else(|| TranscribeError::NotFound("Job not found".to_string()))?;
+
+    if status.is_finished() {
+        return Err(TranscribeError::Conflict(format!(
+            "Job is already {}",
+            status.status
+        )));
+    }
+
+    state.queue.cancel_job(&job_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Results for every recording a batch job has finished so far
+pub async fn get_job_results(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> Result<Json<Vec<JobResult>>> {
+    if state.queue.get_job_status(&job_id).await?.is_none() {
+        return Err(TranscribeError::NotFound("Job not found".to_string()));
+    }
+
+    let mut results = state.queue.get_job_results(&job_id).await?;
+    for job_result in &mut results {
+        resolve_spilled_result(&state, &mut job_result.result).await?;
+    }
+    Ok(Json(results))
+}
+
+#[derive(Debug, Serialize)]
+pub struct DownloadUrlResponse {
+    pub url: String,
+    pub expires_in: u32,
+}
+
+/// DELETE /result/{recording_id}: erase a recording's result now rather than
+/// when it expires (admin only)
+pub async fn delete_result(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(recording_id): Path<String>,
+) -> Result<StatusCode> {
+    require_admin(&state, &headers)?;
+
+    if !erase_result(&state, &recording_id).await? {
+        return Err(TranscribeError::NotFound("Result not found".to_string()));
+    }
+
+    info!(recording_id = %recording_id, "Deleted result");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Query parameters of `DELETE /results`
+#[derive(Debug, Deserialize)]
+pub struct DeleteResultsParams {
+    /// Erase the results of every recording whose ID starts with this
+    pub prefix: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteResultsResponse {
+    pub deleted: usize,
+}
+
+/// DELETE /results?prefix=...: erase the results of many recordings at once,
+/// e.g. every recording of a meeting (admin only)
+pub async fn delete_results(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<DeleteResultsParams>,
+) -> Result<Json<DeleteResultsResponse>> {
+    require_admin(&state, &headers)?;
+
+    // An empty prefix would erase every result
+    if params.prefix.is_empty() {
+        return Err(TranscribeError::InvalidRequest(
+            "prefix must not be empty".to_string(),
+        ));
+    }
+
+    let mut deleted = 0;
+    for recording_id in state.queue.scan_result_ids(&params.prefix).await? {
+        if erase_result(&state, &recording_id).await? {
+            deleted += 1;
+        }
+    }
+
+    info!(prefix = %params.prefix, deleted, "Deleted results");
+    Ok(Json(DeleteResultsResponse { deleted }))
+}
+
+/// Delete the bucket objects holding a recording's result, then the result
+/// itself. False when there was no result.
+async fn erase_result(state: &AppState, recording_id: &str) -> Result<bool> {
+    // Objects first: if deleting one fails, the result still points at it
+    // and a retry finds it again
+    for object_key in state.queue.get_result_objects(recording_id).await? {
+        state.storage.delete_object(&object_key).await?;
+    }
+    state.queue.delete_transcription_result(recording_id).await
+}
+
+/// Short-lived presigned URL for a recording's result JSON in the bucket,
+/// written when the request set `store_result_key`
+pub async fn get_result_download_url(
+    State(state): State<Arc<AppState>>,
+    Path(recording_id): Path<String>,
+) -> Result<Json<DownloadUrlResponse>> {
+    let object_key = state
+        .queue
+        .get_result_artifact(&recording_id)
+        .await?
+        .ok_or_else(|| TranscribeError::NotFound("No stored result for recording".to_string()))?;
+
+    let url = state
+        .storage
+        .presign_get(&object_key, None, state.result_url_expiry_secs)
+        .await?;
+
+    Ok(Json(DownloadUrlResponse {
+        url,
+        expires_in: state.result_url_expiry_secs,
+    }))
+}
+
+/// Time range (seconds) of a stored result to return; open-ended when unset
+#[derive(Debug, Deserialize)]
+pub struct ResultRange {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+/// Stored result of a single recording from a batch job. With `start`
+/// and/or `end`, only segments overlapping that range (including ones
+/// straddling its edges) and their text are returned.
+pub async fn get_result(
+    State(state): State<Arc<AppState>>,
+    Path(recording_id): Path<String>,
+    Query(range): Query<ResultRange>,
+) -> Result<Json<TranscriptionStatus>> {
+    let start = range.start.unwrap_or(f64::NEG_INFINITY);
+    let end = range.end.unwrap_or(f64::INFINITY);
+    if start.is_nan() || end.is_nan() || start > end {
+        return Err(TranscribeError::InvalidRequest(
+            "start must not be after end".to_string(),
+        ));
+    }
+
+    let mut result = load_transcription_result(&state, &recording_id)
+        .await?
+        .ok_or_else(|| TranscribeError::NotFound("Result not found".to_string()))?;
+
+    if range.start.is_some() || range.end.is_some() {
+        let segments: Vec<SegmentResponse> = result
+            .segments
+            .take()
+            .ok_or_else(|| {
+                TranscribeError::NotFound("Result was stored without segments".to_string())
+            })?
+            .into_iter()
+            .filter(|s| s.end >= start && s.start <= end)
+            .collect();
+
+        // Trim everything else to the range too, so nothing large is shipped
+        let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        result.text = Some(texts.join(" "));
+        result.srt = None;
+        result.vtt = None;
+        result.segments = Some(segments);
+    }
+
+    Ok(Json(result))
+}
+
+/// Server-sent `progress` events for a batch job, starting with its current
+/// status and ending once it finishes
+pub async fn subscribe_job_progress(
+    State(state): State<Arc<AppState>>,
+    Path(job_id): Path<String>,
+) -> Result<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    // Subscribe before reading the current status so no update falls in between
+    let updates = state.queue.subscribe_job_progress(&job_id).await?;
+    let current = state
+        .queue
+        .get_job_status(&job_id)
+        .await?
+        .ok_or_else(|| TranscribeError::NotFound("Job not found".to_string()))?;
+
+    let stream = futures::stream::once(async { current })
+        .chain(updates)
+        .scan(false, |finished, status| {
+            if *finished {
+                return futures::future::ready(None);
+            }
+            *finished = status.is_finished();
+            futures::future::ready(Some(status))
+        })
+        .filter_map(|status| async move {
+            Event::default()
+                .event("progress")
+                .json_data(&status)
+                .ok()
+                .map(Ok)
+        });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Delivery state of a recording's completion callback
+pub async fn get_callback_delivery(
+    State(state): State<Arc<AppState>>,
+    Path(recording_id): Path<String>,
+) -> Result<Json<CallbackDelivery>> {
+    state
+        .queue
+        .get_callback(&recording_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| TranscribeError::NotFound("No callback for recording".to_string()))
+}
+
+/// Recordings that failed in batch jobs, oldest first
+pub async fn get_dead_letters(State(state): State<Arc<AppState>>) -> Result<Json<Vec<DeadLetter>>> {
+    Ok(Json(state.queue.get_dead_letters().await?))
+}
+
+/// Retry the oldest dead-letter entries (up to the batch size limit) as a
+/// new batch job. Entries leave the queue only once the job has started;
+/// call again to retry the rest.
+pub async fn retry_dead_letters(State(state): State<Arc<AppState>>) -> Result<Json<BatchResponse>> {
+    let (entries, stored) = state.queue.peek_dead_letters(state.max_batch_size).await?;
+    let requests: Vec<TranscribeRequest> = entries.into_iter().map(|entry| entry.request).collect();
+
+    if requests.is_empty() {
+        // Only unreadable entries (if any) are left; they'd never retry
+        state.queue.remove_dead_letters(&stored).await?;
+        return Err(TranscribeError::NotFound(
+            "Dead-letter queue is empty".to_string(),
+        ));
+    }
+
+    let batch = start_batch(&state, requests, None).await?;
+    if let Err(e) = state.queue.remove_dead_letters(&stored).await {
+        error!("Failed to remove retried dead-letter entries: {}", e);
+    }
+
+    Ok(Json(batch))
+}
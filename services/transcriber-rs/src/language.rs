@@ -0,0 +1,150 @@
+use crate::transcriber::Segment;
+
+/// Fewest words in the detection window for a guess; shorter clips keep the
+/// requested or default language
+const MIN_WORDS: usize = 8;
+
+/// Fewest function words recognized before a guess is made
+const MIN_HITS: usize = 3;
+
+/// Common function words per language. Parakeet TDT v3 transcribes whatever
+/// language is spoken without reporting which, so the language is recognized
+/// from the words it produced.
+const FUNCTION_WORDS: &[(&str, &[&str])] = &[
+    (
+        "en",
+        &[
+            "the", "and", "is", "are", "was", "you", "that", "this", "with", "have", "not", "what",
+            "for", "of", "to", "i'm", "it's", "they",
+        ],
+    ),
+    (
+        "de",
+        &[
+            "der", "die", "und", "ist", "nicht", "das", "ich", "sie", "wir", "ein", "eine", "mit",
+            "auch", "auf", "es", "zu", "den", "haben",
+        ],
+    ),
+    (
+        "fr",
+        &[
+            "le", "la", "les", "et", "est", "une", "je", "vous", "nous", "pas", "que", "des",
+            "dans", "pour", "c'est", "ce", "il", "du",
+        ],
+    ),
+    (
+        "es",
+        &[
+            "el", "la", "los", "las", "y", "es", "que", "una", "no", "por", "para", "con", "lo",
+            "del", "pero", "está", "muy", "yo",
+        ],
+    ),
+    (
+        "it",
+        &[
+            "il", "che", "di", "e", "è", "non", "per", "una", "sono", "con", "la", "gli", "anche",
+            "ma", "questo", "io", "del", "molto",
+        ],
+    ),
+    (
+        "pt",
+        &[
+            "o", "a", "os", "que", "não", "é", "uma", "um", "com", "para", "por", "do", "da",
+            "mas", "você", "eu", "isso", "está",
+        ],
+    ),
+    (
+        "nl",
+        &[
+            "de", "het", "een", "en", "is", "niet", "ik", "dat", "je", "van", "wat", "zijn", "ook",
+            "met", "maar", "op", "we", "er",
+        ],
+    ),
+    (
+        "pl",
+        &[
+            "i", "w", "nie", "się", "to", "na", "jest", "że", "z", "do", "jak", "ale", "co", "tak",
+            "jestem", "czy", "już", "tylko",
+        ],
+    ),
+    (
+        "ru",
+        &[
+            "и", "в", "не", "что", "на", "я", "это", "как", "он", "с", "но", "мы", "вы", "так",
+            "то", "все", "был", "да",
+        ],
+    ),
+    (
+        "uk",
+        &[
+            "і", "в", "не", "що", "на", "я", "це", "як", "він", "з", "але", "ми", "ви", "так",
+            "та", "є", "був", "ні",
+        ],
+    ),
+    (
+        "sv",
+        &[
+            "och", "att", "det", "är", "som", "en", "på", "jag", "inte", "med", "för", "har", "vi",
+            "de", "om", "ett", "kan", "så",
+        ],
+    ),
+    (
+        "da",
+        &[
+            "og", "at", "det", "er", "som", "en", "på", "jeg", "ikke", "med", "for", "har", "vi",
+            "de", "om", "et", "kan", "så",
+        ],
+    ),
+];
+
+/// A detected language and how sure the detector is of it
+#[derive(Debug, Clone, Copy)]
+pub struct Detection {
+    /// ISO 639-1 code
+    pub language: &'static str,
+    /// Share of the recognized function words that belong to `language` (0-1)
+    pub confidence: f32,
+}
+
+/// Identify the language of the words spoken in the first `window_secs` of
+/// a transcript. `None` when there are too few words to tell.
+pub fn detect(words: &[Segment], window_secs: f64) -> Option<Detection> {
+    let window_end = words.first()?.start + window_secs;
+    let window: Vec<String> = words
+        .iter()
+        .take_while(|word| word.start < window_end)
+        .map(|word| {
+            word.text
+                .trim()
+                .trim_matches(|c: char| !c.is_alphanumeric() && c != '\'')
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect();
+    if window.len() < MIN_WORDS {
+        return None;
+    }
+
+    let hits: Vec<(&'static str, usize)> = FUNCTION_WORDS
+        .iter()
+        .map(|(language, function_words)| {
+            let count = window
+                .iter()
+                .filter(|word| function_words.contains(&word.as_str()))
+                .count();
+            (*language, count)
+        })
+        .collect();
+    let total: usize = hits.iter().map(|(_, count)| count).sum();
+    let (language, best) = hits
+        .into_iter()
+        .fold(("", 0), |best, hit| if hit.1 > best.1 { hit } else { best });
+    if best < MIN_HITS {
+        return None;
+    }
+
+    Some(Detection {
+        language,
+        confidence: best as f32 / total as f32,
+    })
+}
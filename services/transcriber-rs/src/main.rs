@@ -1,93 +1,444 @@
+mod auth;
+mod cache;
+mod callback;
+mod chunking;
 mod config;
+mod diarization;
+mod engine;
+mod error;
+mod fetch;
 mod handlers;
+mod language;
+mod metrics;
+mod models;
 mod queue;
+mod rate_limit;
+mod realtime;
+mod request_id;
+mod segments;
+mod silence;
 mod storage;
+mod subtitles;
+mod telemetry;
 mod transcriber;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use axum::{
-    routing::{get, post},
+    extract::DefaultBodyLimit,
+    http::HeaderName,
+    middleware,
+    routing::{delete, get, post},
     Router,
 };
-use std::net::SocketAddr;
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use tower_http::cors::{Any, CorsLayer};
+use opentelemetry::trace::TracerProvider as _;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{RwLock, Semaphore};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, Any, CorsLayer};
 use tower_http::trace::TraceLayer;
-use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
+use tracing::{error, info, warn};
+use tracing_subscriber::filter::EnvFilter;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, Layer};
 
+use callback::Callbacks;
 use config::Config;
+use engine::{Backend, EngineSpec, ModelPrecision};
 use handlers::AppState;
 use queue::Queue;
+use rate_limit::RateLimit;
 use storage::Storage;
-use transcriber::Transcriber;
-
-#[tokio::main]
-async fn main() -> Result<()> {
-    // Initialize logging
-    FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .with_target(false)
+use transcriber::{AudioFormat, TranscribeOptions, Transcriber};
+
+fn main() -> Result<()> {
+    // `--file <path>` transcribes one local file and exits instead of serving
+    let cli_file = file_arg()?;
+
+    // Load configuration (before the runtime, which it sizes)
+    let config = match std::env::var("CONFIG_FILE") {
+        Ok(path) => Config::from_file(Path::new(&path))?,
+        Err(_) => Config::from_env(),
+    };
+
+    // Initialize logging (after config, which chooses the format), and trace
+    // export when a collector is configured
+    let tracer_provider = config
+        .otlp_endpoint
+        .as_deref()
+        .map(|endpoint| telemetry::init_tracer_provider(endpoint, &config.otel_service_name))
+        .transpose()?;
+    let otel_layer = tracer_provider
+        .as_ref()
+        .map(|provider| tracing_opentelemetry::layer().with_tracer(provider.tracer("transcriber")));
+    // In CLI mode stdout carries the result, so logs go to stderr
+    let writer = if cli_file.is_some() {
+        BoxMakeWriter::new(std::io::stderr)
+    } else {
+        BoxMakeWriter::new(std::io::stdout)
+    };
+    let fmt_layer = if config.log_format == "json" {
+        fmt::layer()
+            .json()
+            .with_target(false)
+            .with_writer(writer)
+            .boxed()
+    } else {
+        fmt::layer().with_target(false).with_writer(writer).boxed()
+    };
+    // RUST_LOG wins over LOG_LEVEL, so one deployment can be debugged
+    // without changing its config
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log_level));
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt_layer)
+        .with(otel_layer)
         .init();
 
+    // ffmpeg, model loading and inference all run on the blocking pool; bound
+    // it so a burst of requests can't start hundreds of CPU-bound threads
+    let result = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .max_blocking_threads(config.blocking_threads.max(1))
+        .build()?
+        .block_on(async {
+            match cli_file {
+                Some(path) => transcribe_file(config, &path).await,
+                None => run(config).await,
+            }
+        });
+
+    // Flush spans still buffered by the batch exporter
+    if let Some(provider) = tracer_provider {
+        if let Err(e) = provider.shutdown() {
+            warn!("Failed to flush traces: {}", e);
+        }
+    }
+    result
+}
+
+async fn run(config: Config) -> Result<()> {
     info!("Starting TinkerVoid Transcriber Service (Rust)");
 
-    // Load configuration
-    let config = Config::from_env();
+    let metrics_handle = metrics::install_recorder()?;
+
+    config.validate()?;
     info!("Configuration loaded");
 
+    transcriber::check_ffmpeg()?;
+    info!("ffmpeg found");
+    match transcriber::check_ffprobe() {
+        Ok(()) => info!("ffprobe found"),
+        Err(e) => warn!("{}; long files are only rejected after conversion", e),
+    }
+
     // Initialize components
     let storage = Storage::new(&config)?;
+    storage.ensure_bucket(config.minio_create_bucket).await?;
     info!("Storage client initialized");
 
-    let queue = Queue::new(&config.redis_url)?;
-    info!("Redis queue initialized");
-
-    // Initialize transcriber
-    let mut transcriber = Transcriber::new();
-
-    // Load model
-    let model_path = PathBuf::from(&config.model_path);
-    transcriber.load_model(&model_path).await?;
+    let queue = Queue::new(&config)?;
+    let redis_available = queue
+        .connect(Duration::from_secs(config.redis_startup_wait_secs))
+        .await;
+    if redis_available {
+        info!("Redis queue initialized");
+    } else {
+        warn!(
+            "Redis unavailable after {}s; running degraded: synchronous transcription works \
+             without caching, batch endpoints return 503 until Redis is reachable",
+            config.redis_startup_wait_secs
+        );
+    }
 
     // Create shared state
     let state = Arc::new(AppState {
-        transcriber: RwLock::new(transcriber),
+        transcriber: RwLock::new(build_transcriber(&config)),
         storage,
         queue,
+        callbacks: Callbacks::new(&config)?,
+        http_client: fetch::http_client(&config)?,
         bucket_name: config.minio_bucket.clone(),
+        store_subtitles: config.store_subtitles,
+        segment_gap_secs: config.segment_gap_secs,
+        default_language: config.default_language.clone(),
+        transcription_slots: Arc::new(Semaphore::new(config.max_concurrent_transcriptions)),
+        permit_timeout: Duration::from_secs(config.permit_timeout_secs),
+        metrics: metrics_handle,
+        max_upload_bytes: config.max_upload_bytes,
+        in_memory_max_bytes: config.in_memory_max_bytes,
+        max_inline_result_bytes: config.max_inline_result_bytes,
+        max_download_bytes: config.max_download_bytes,
+        download_timeout: Duration::from_secs(config.download_timeout_secs),
+        max_multi_files: config.max_multi_files,
+        max_batch_size: config.max_batch_size,
+        batch_wait_timeout: Duration::from_secs(config.batch_wait_timeout_secs),
+        cache_ttl_secs: config.cache_ttl_secs,
+        idempotency_ttl_secs: config.idempotency_ttl_secs,
+        filler_words: config.filler_words.clone(),
+        merge_gap_secs: config.merge_gap_secs,
+        silence_threshold_db: config.silence_threshold_db,
+        punctuation_pause_secs: config.punctuation_pause_secs,
+        normalize_text: config.normalize_text,
+        unspaced_languages: config.unspaced_languages.clone(),
+        language_detect_secs: config.language_detect_secs,
+        result_url_expiry_secs: config.result_url_expiry_secs,
+        models_dir: PathBuf::from(&config.models_dir),
+        temp_dir: temp_dir(&config),
+        admin_token: config.admin_token.clone(),
+        api_key: config.api_key.clone(),
+        rate_limit: config.rate_limit_per_minute.map(|per_minute| RateLimit {
+            per_minute,
+            burst: config.rate_limit_burst.unwrap_or(per_minute),
+        }),
+        started_at: Instant::now(),
+        activity: metrics::Activity::default(),
+        in_flight: Mutex::new(HashMap::new()),
+        in_progress: Mutex::new(HashMap::new()),
+        deep_health: tokio::sync::Mutex::new(None),
     });
 
+    // Load model in the background so liveness probes pass while it warms up;
+    // /ready reports 503 until it is loaded (or, with LAZY_LOAD, defer it to
+    // the first request)
+    let model_path = PathBuf::from(&config.model_path);
+    let lazy_load = config.lazy_load;
+    let warmup_on_start = config.warmup_on_start;
+    let diarization_model_path = config
+        .diarization_enabled
+        .then(|| PathBuf::from(&config.diarization_model_path));
+    let loader_state = state.clone();
+    tokio::spawn(async move {
+        let transcriber = loader_state.transcriber.read().await;
+        if let Some(path) = diarization_model_path {
+            if let Err(e) = transcriber.load_diarizer(&path).await {
+                error!("Failed to load diarization model: {}", e);
+                std::process::exit(1);
+            }
+        }
+        if lazy_load {
+            transcriber.load_model_lazily(&model_path);
+        } else if let Err(e) = transcriber.load_model(&model_path).await {
+            error!("Failed to load model: {}", e);
+            std::process::exit(1);
+        }
+        if warmup_on_start && !lazy_load {
+            match transcriber.warm_up(&loader_state.temp_dir).await {
+                Ok(elapsed) => info!("Model warmed up in {:.2}s", elapsed.as_secs_f64()),
+                Err(e) => warn!("Model warmup failed: {}", e),
+            }
+        }
+    });
+
+    if !redis_available {
+        let reconnect_state = state.clone();
+        tokio::spawn(async move {
+            reconnect_state.queue.reconnect().await;
+            info!("Redis reachable; leaving degraded mode");
+        });
+    }
+
+    // Start batch workers
+    for _ in 0..config.batch_workers {
+        tokio::spawn(handlers::run_batch_worker(state.clone()));
+    }
+    tokio::spawn(handlers::run_recovery_sweep(state.clone()));
+    tokio::spawn(handlers::run_callback_worker(state.clone()));
+
     // Build router
     let app = Router::new()
         .route("/health", get(handlers::health))
+        .route("/live", get(handlers::live))
+        .route("/ready", get(handlers::ready))
+        .route("/metrics", get(handlers::get_metrics))
         .route("/transcribe", post(handlers::transcribe))
+        .route(
+            "/transcribe/{request_id}",
+            delete(handlers::cancel_transcription),
+        )
         .route("/transcribe/batch", post(handlers::transcribe_batch))
-        .route("/job/{job_id}", get(handlers::get_job_status))
-        .layer(TraceLayer::new_for_http())
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
+        .route("/transcribe/multi", post(handlers::transcribe_multi))
+        .route("/transcribe/stream", post(handlers::transcribe_stream))
+        .route("/validate", post(handlers::validate_files))
+        .route("/ws/transcribe", get(handlers::live_transcribe))
+        .route(
+            "/transcribe/upload",
+            post(handlers::transcribe_upload).layer(DefaultBodyLimit::max(config.max_upload_bytes)),
+        )
+        .route(
+            "/job/{job_id}",
+            get(handlers::get_job_status).delete(handlers::cancel_job),
+        )
+        .route("/job/{job_id}/results", get(handlers::get_job_results))
+        .route(
+            "/job/{job_id}/subscribe",
+            get(handlers::subscribe_job_progress),
+        )
+        .route(
+            "/result/{recording_id}",
+            get(handlers::get_result).delete(handlers::delete_result),
         )
+        .route("/results", delete(handlers::delete_results))
+        .route(
+            "/result/{recording_id}/download-url",
+            get(handlers::get_result_download_url),
+        )
+        .route(
+            "/callbacks/{recording_id}",
+            get(handlers::get_callback_delivery),
+        )
+        .route("/deadletter", get(handlers::get_dead_letters))
+        .route("/deadletter/retry", post(handlers::retry_dead_letters))
+        .route("/model/reload", post(handlers::reload_model))
+        .route("/models", get(handlers::list_models))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit::limit_requests,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            auth::require_api_key,
+        ))
+        // Honors Accept-Encoding; the default predicate leaves SSE streams
+        // (and tiny bodies and images) uncompressed so events
+        // aren't held back in the encoder's buffer
+        .layer(CompressionLayer::new())
+        .layer(middleware::from_fn(request_id::propagate))
+        .layer(TraceLayer::new_for_http().make_span_with(telemetry::request_span))
+        .layer(cors_layer(&config.cors_allowed_origins)?)
         .with_state(state);
 
     // Start server
-    let addr = SocketAddr::from(([0, 0, 0, 0], config.port));
+    let host: IpAddr = config.host.parse()?;
+    let addr = SocketAddr::new(host, config.port);
     info!("Server listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    // Peer addresses identify clients for rate limiting
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown_signal())
+    .await?;
 
     info!("Server shutdown complete");
     Ok(())
 }
 
+/// Any origin without credentials by default (for local development), or
+/// only the configured origins, with credentials
+fn cors_layer(allowed_origins: &[String]) -> Result<CorsLayer> {
+    if allowed_origins.is_empty() {
+        return Ok(CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .expose_headers([HeaderName::from_static(request_id::REQUEST_ID_HEADER)]));
+    }
+
+    let origins = allowed_origins
+        .iter()
+        .map(|origin| origin.parse())
+        .collect::<Result<Vec<_>, _>>()?;
+    info!("CORS restricted to {}", allowed_origins.join(", "));
+    // Wildcards aren't allowed alongside credentials, so echo the preflight's
+    Ok(CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods(AllowMethods::mirror_request())
+        .allow_headers(AllowHeaders::mirror_request())
+        .expose_headers([HeaderName::from_static(request_id::REQUEST_ID_HEADER)])
+        .allow_credentials(true))
+}
+
+/// Transcribe one local file and print the result as JSON on stdout, without
+/// the HTTP server, Redis or MinIO
+async fn transcribe_file(config: Config, path: &Path) -> Result<()> {
+    config.validate()?;
+    transcriber::check_ffmpeg()?;
+
+    let transcriber = build_transcriber(&config);
+    if config.diarization_enabled {
+        transcriber
+            .load_diarizer(Path::new(&config.diarization_model_path))
+            .await?;
+    }
+    transcriber
+        .load_model(Path::new(&config.model_path))
+        .await?;
+
+    let options = TranscribeOptions {
+        language: config.default_language.clone(),
+        diarize: config.diarization_enabled,
+        ..Default::default()
+    };
+    let result = transcriber.transcribe(path, &options).await?;
+
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    Ok(())
+}
+
+/// The transcription pipeline as configured
+fn build_transcriber(config: &Config) -> Transcriber {
+    Transcriber::new(
+        AudioFormat {
+            sample_rate: config.audio_sample_rate,
+            channels: config.audio_channels,
+            filter: config.audio_filter.clone(),
+            normalize: config.audio_normalize,
+            denoise: config.audio_denoise,
+            drift_tolerance_secs: config.drift_tolerance_secs,
+            rescale_drift: config.rescale_drift,
+        },
+        config.transcription_timeout_secs.map(Duration::from_secs),
+        config.profile_chunking(),
+        config.max_audio_seconds,
+        config.window_workers,
+        EngineSpec {
+            backend: Backend::parse(&config.backend).unwrap_or_default(),
+            precision: ModelPrecision::parse(&config.model_precision).unwrap_or_default(),
+            temp_dir: temp_dir(config),
+        },
+    )
+}
+
+/// TEMP_DIR, or the system temp dir when unset
+fn temp_dir(config: &Config) -> PathBuf {
+    config
+        .temp_dir
+        .as_ref()
+        .map(PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+}
+
+/// The path given with `--file <path>` (or `--file=<path>`), if any
+fn file_arg() -> Result<Option<PathBuf>> {
+    let mut args = std::env::args().skip(1);
+    let mut file = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--file" => match args.next() {
+                Some(path) => file = Some(PathBuf::from(path)),
+                None => bail!("--file needs a path"),
+            },
+            _ => match arg.strip_prefix("--file=") {
+                Some(path) => file = Some(PathBuf::from(path)),
+                None => bail!(
+                    "Unknown argument {}; usage: transcriber-rs [--file <path>]",
+                    arg
+                ),
+            },
+        }
+    }
+    Ok(file)
+}
+
 async fn shutdown_signal() {
     tokio::signal::ctrl_c()
         .await
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const TRANSCRIPTIONS_TOTAL: &str = "transcriptions_total";
+const TRANSCRIPTION_FAILURES_TOTAL: &str = "transcription_failures_total";
+const TRANSCRIPTION_DURATION_SECONDS: &str = "transcription_duration_seconds";
+const DOWNLOAD_DURATION_SECONDS: &str = "download_duration_seconds";
+const TRANSCRIPTIONS_IN_FLIGHT: &str = "transcriptions_in_flight";
+
+/// Histogram buckets in seconds, from short clips up to hour-long recordings
+const DURATION_BUCKETS: &[f64] = &[
+    0.1, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0,
+];
+
+/// Install the global Prometheus recorder and return a handle for rendering
+pub fn install_recorder() -> Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Suffix("duration_seconds".to_string()),
+            DURATION_BUCKETS,
+        )?
+        .install_recorder()?;
+
+    Ok(handle)
+}
+
+/// Record the outcome of a transcription; `kind` is "single", "batch", "stream" or "live"
+pub fn record_transcription(kind: &'static str, success: bool, seconds: f64) {
+    counter!(TRANSCRIPTIONS_TOTAL, "kind" => kind).increment(1);
+    if !success {
+        counter!(TRANSCRIPTION_FAILURES_TOTAL, "kind" => kind).increment(1);
+    }
+    histogram!(TRANSCRIPTION_DURATION_SECONDS, "kind" => kind).record(seconds);
+}
+
+pub fn record_download(seconds: f64) {
+    histogram!(DOWNLOAD_DURATION_SECONDS).record(seconds);
+}
+
+/// Transcriptions running on this instance, counted for the health output
+/// (the gauge is only visible to whoever scrapes `/metrics`)
+#[derive(Default)]
+pub struct Activity {
+    total: AtomicUsize,
+    batch: AtomicUsize,
+}
+
+impl Activity {
+    /// Count a transcription as in flight until the returned guard is dropped
+    pub fn start(&self, kind: &'static str) -> InFlight<'_> {
+        gauge!(TRANSCRIPTIONS_IN_FLIGHT, "kind" => kind).increment(1.0);
+        self.total.fetch_add(1, Ordering::Relaxed);
+        if kind == "batch" {
+            self.batch.fetch_add(1, Ordering::Relaxed);
+        }
+        InFlight {
+            activity: self,
+            kind,
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    pub fn batch_in_flight(&self) -> usize {
+        self.batch.load(Ordering::Relaxed)
+    }
+}
+
+/// Tracks a transcription as in flight for as long as it is alive
+pub struct InFlight<'a> {
+    activity: &'a Activity,
+    kind: &'static str,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        gauge!(TRANSCRIPTIONS_IN_FLIGHT, "kind" => self.kind).decrement(1.0);
+        self.activity.total.fetch_sub(1, Ordering::Relaxed);
+        if self.kind == "batch" {
+            self.activity.batch.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
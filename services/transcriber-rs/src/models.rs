@@ -0,0 +1,53 @@
+use serde::Serialize;
+use std::path::Path;
+
+/// A Parakeet model directory found on disk
+#[derive(Debug, Serialize)]
+pub struct ModelInfo {
+    pub name: String,
+    pub path: String,
+    /// Total size of the files in the directory
+    pub size_bytes: u64,
+    /// Whether this is the model the transcriber is using
+    pub active: bool,
+}
+
+/// List the Parakeet models directly under `models_dir`: subdirectories
+/// holding a `vocab.txt` next to their ONNX files. Blocking; call from a
+/// blocking task.
+pub fn discover(models_dir: &Path, active: Option<&Path>) -> std::io::Result<Vec<ModelInfo>> {
+    let active = active.and_then(|path| path.canonicalize().ok());
+
+    let mut models = Vec::new();
+    for entry in std::fs::read_dir(models_dir)? {
+        let path = entry?.path();
+        if !path.join("vocab.txt").is_file() {
+            continue;
+        }
+
+        models.push(ModelInfo {
+            name: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: path.display().to_string(),
+            size_bytes: directory_size(&path)?,
+            active: active.is_some() && path.canonicalize().ok() == active,
+        });
+    }
+
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(models)
+}
+
+/// Sum of the sizes of the files directly inside `dir`
+fn directory_size(dir: &Path) -> std::io::Result<u64> {
+    let mut size = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
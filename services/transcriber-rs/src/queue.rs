@@ -1,7 +1,19 @@
-use anyhow::{Context, Result};
-use redis::AsyncCommands;
+use futures::{Stream, StreamExt};
+use redis::aio::ConnectionManager;
+use redis::{AsyncCommands, ErrorKind, RedisError, RedisResult};
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::error::{Result, TranscribeError};
+use crate::handlers::{SegmentResponse, TranscribeRequest};
+use crate::rate_limit::RateLimit;
+use crate::transcriber::TranscriptionResult;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JobStatus {
@@ -10,107 +22,1151 @@ pub struct JobStatus {
     pub total: Option<u32>,
 }
 
+impl JobStatus {
+    /// Whether the job has stopped changing
+    pub fn is_finished(&self) -> bool {
+        matches!(self.status.as_str(), "completed" | "failed" | "cancelled")
+    }
+
+    fn from_hash(data: &HashMap<String, String>) -> Self {
+        Self {
+            status: data.get("status").cloned().unwrap_or_default(),
+            current: data.get("current").and_then(|s| s.parse().ok()),
+            total: data.get("total").and_then(|s| s.parse().ok()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TranscriptionStatus {
     pub status: String,
     pub text: Option<String>,
     pub duration: Option<f64>,
     pub error: Option<String>,
+    /// Pre-rendered SubRip subtitles (when subtitle storage is enabled)
+    pub srt: Option<String>,
+    /// Pre-rendered WebVTT subtitles (when subtitle storage is enabled)
+    pub vtt: Option<String>,
+    /// Timed segments, so clients can fetch a time range of a long transcript
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segments: Option<Vec<SegmentResponse>>,
+    /// The request's `metadata`, unchanged
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+    /// Bucket key of the full result, when it was too large to keep in Redis
+    #[serde(skip)]
+    pub result_ref: Option<String>,
 }
 
+/// A stored result together with the recording it belongs to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobResult {
+    pub recording_id: String,
+    #[serde(flatten)]
+    pub result: TranscriptionStatus,
+}
+
+/// A batch recording that failed, kept so it can be inspected and retried
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub recording_id: String,
+    pub error: String,
+    /// Unix timestamp (seconds) of the failure
+    pub failed_at: u64,
+    /// Original request, resubmitted as-is on retry
+    pub request: TranscribeRequest,
+}
+
+const DEAD_LETTER_KEY: &str = "deadletter";
+
+/// Delivery state of a recording's completion callback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallbackDelivery {
+    pub recording_id: String,
+    pub url: String,
+    /// `pending`, `delivered` or `failed`
+    pub status: String,
+    pub attempts: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Unix timestamp (seconds) of the last change
+    pub updated_at: u64,
+}
+
+/// Recording IDs of callbacks to attempt, scored by when they are due
+const CALLBACK_DUE_KEY: &str = "callbacks:due";
+
+/// One recording of a batch job waiting in the shared work queue
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedItem {
+    pub job_id: String,
+    /// Position within the job; also keeps identical requests distinct in the set
+    pub index: u32,
+    pub total: u32,
+    pub request: TranscribeRequest,
+}
+
+/// An item taken from the work queue, kept until it is acknowledged
+#[derive(Debug, Clone)]
+pub struct ClaimedItem {
+    pub item: QueuedItem,
+    /// The item exactly as stored, which identifies it in the processing set
+    member: String,
+}
+
+const WORK_QUEUE_KEY: &str = "queue";
+const WORK_QUEUE_SEQ_KEY: &str = "queue:seq";
+/// Items being processed, scored by their last heartbeat (Unix seconds)
+const WORK_PROCESSING_KEY: &str = "queue:processing";
+
+/// How often `dequeue` looks for work while the queue is empty
+const DEQUEUE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Delay between attempts to reach Redis while it is unavailable
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long an idempotency key stays claimed by a request that never finishes
+const IDEMPOTENCY_PENDING_TTL_SECS: u64 = 3600;
+
+/// Score gap between adjacent priorities; sequence numbers stay below it, and
+/// every score stays exactly representable as an f64
+const PRIORITY_STRIDE: f64 = (1u64 << 40) as f64;
+
 pub struct Queue {
     client: redis::Client,
+    /// Shared multiplexed connection, opened on first use and reconnected
+    /// automatically after a connection error
+    manager: OnceCell<ConnectionManager>,
+    /// Cleared while Redis couldn't be reached at startup; operations then
+    /// fail at once instead of waiting out their retries
+    available: AtomicBool,
+    key_prefix: String,
+    job_ttl_secs: i64,
+    result_ttl_secs: i64,
+    max_retries: u32,
+    retry_base_delay: Duration,
 }
 
 impl Queue {
-    pub fn new(redis_url: &str) -> Result<Self> {
-        let client = redis::Client::open(redis_url).context("Failed to connect to Redis")?;
-        Ok(Self { client })
+    pub fn new(config: &Config) -> Result<Self> {
+        let client = redis::Client::open(config.redis_url.as_str())?;
+        Ok(Self {
+            client,
+            manager: OnceCell::new(),
+            available: AtomicBool::new(true),
+            key_prefix: config.redis_key_prefix.clone(),
+            job_ttl_secs: config.job_ttl_secs as i64,
+            result_ttl_secs: config.result_ttl_secs as i64,
+            max_retries: config.redis_max_retries,
+            retry_base_delay: Duration::from_millis(config.redis_retry_base_ms),
+        })
+    }
+
+    /// Whether Redis is reachable; batch endpoints need it, synchronous
+    /// transcription only uses it for caching
+    pub fn is_available(&self) -> bool {
+        self.available.load(Ordering::Relaxed)
+    }
+
+    /// Fail with a 503 while Redis is unavailable
+    pub fn require_available(&self) -> Result<()> {
+        if self.is_available() {
+            Ok(())
+        } else {
+            Err(TranscribeError::Queue("Redis is unavailable".to_string()))
+        }
     }
 
-    pub async fn set_job_status(&self, job_id: &str, status: &JobStatus) -> Result<()> {
+    /// Try to reach Redis for up to `wait`, recording whether it is available
+    pub async fn connect(&self, wait: Duration) -> bool {
+        let deadline = Instant::now() + wait;
+        loop {
+            match self.ping().await {
+                Ok(()) => break,
+                Err(e) if Instant::now() >= deadline => {
+                    warn!("Redis is unreachable: {}", e);
+                    self.available.store(false, Ordering::Relaxed);
+                    return false;
+                }
+                Err(e) => {
+                    warn!("Waiting for Redis: {}", e);
+                    tokio::time::sleep(RECONNECT_INTERVAL).await;
+                }
+            }
+        }
+        self.available.store(true, Ordering::Relaxed);
+        true
+    }
+
+    /// Keep trying to reach Redis until it answers, then leave degraded mode
+    pub async fn reconnect(&self) {
+        while let Err(e) = self.ping().await {
+            warn!("Redis still unreachable: {}", e);
+            tokio::time::sleep(RECONNECT_INTERVAL).await;
+        }
+        self.available.store(true, Ordering::Relaxed);
+    }
+
+    /// Round-trip to the server once, bypassing retries and the availability check
+    async fn ping(&self) -> RedisResult<()> {
         let mut conn = self
-            .client
-            .get_multiplexed_async_connection()
-            .await
-            .context("Failed to get Redis connection")?;
+            .manager
+            .get_or_try_init(|| self.client.get_connection_manager())
+            .await?
+            .clone();
+        redis::cmd("PING").query_async(&mut conn).await
+    }
+
+    /// Full key for `name` within this deployment's namespace
+    fn key(&self, name: &str) -> String {
+        format!("{}:{}", self.key_prefix, name)
+    }
 
-        let key = format!("transcribe:job:{}", job_id);
+    /// Run Redis commands on the shared connection, retrying connection
+    /// failures with exponential backoff. The whole operation is re-run on
+    /// retry, so it must be safe to repeat; operations that aren't use
+    /// `connection` instead.
+    async fn with_retries<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut(ConnectionManager) -> Fut,
+        Fut: Future<Output = RedisResult<T>>,
+    {
+        self.require_available()?;
 
-        conn.hset::<_, _, _, ()>(&key, "status", &status.status)
+        let mut attempt = 0;
+        loop {
+            let manager = self
+                .manager
+                .get_or_try_init(|| self.client.get_connection_manager())
+                .await;
+            let result = match manager {
+                Ok(manager) => operation(manager.clone()).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    attempt += 1;
+                    let delay = self.retry_base_delay * 2u32.saturating_pow(attempt - 1);
+                    warn!(
+                        "Redis request failed (attempt {}/{}): {}; retrying in {:?}",
+                        attempt, self.max_retries, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// A connection, retrying only the connect. For commands that must not
+    /// run twice when a reply is lost (counters, pushes, pops, claims).
+    async fn connection(&self) -> Result<ConnectionManager> {
+        self.with_retries(|conn| async move { Ok(conn) }).await
+    }
+
+    /// Update a job's status. A cancelled job stays cancelled even if a
+    /// worker reports on it afterwards; returns `false` when it was.
+    pub async fn set_job_status(&self, job_id: &str, status: &JobStatus) -> Result<bool> {
+        let key = &self.key(&format!("job:{}", job_id));
+        let current = status.current.map(|c| c.to_string()).unwrap_or_default();
+        let total = status.total.map(|t| t.to_string()).unwrap_or_default();
+        let (current, total) = (&current, &total);
+
+        let updated: u8 = self
+            .with_retries(|mut conn| async move {
+                let updated = redis::Script::new(SET_JOB_STATUS_SCRIPT)
+                    .key(key)
+                    .arg(&status.status)
+                    .arg(current)
+                    .arg(total)
+                    .arg(self.job_ttl_secs)
+                    .invoke_async(&mut conn)
+                    .await?;
+
+                self.publish_job_status(&mut conn, job_id).await?;
+                Ok(updated)
+            })
             .await?;
 
-        if let Some(current) = status.current {
-            conn.hset::<_, _, _, ()>(&key, "current", current.to_string())
+        info!("Updated job {} status: {:?}", job_id, status);
+        Ok(updated == 1)
+    }
+
+    /// Mark a job cancelled; workers skip its remaining items
+    pub async fn cancel_job(&self, job_id: &str) -> Result<()> {
+        let key = &self.key(&format!("job:{}", job_id));
+
+        self.with_retries(|mut conn| async move {
+            conn.hset_multiple::<_, _, _, ()>(key, &[("status", "cancelled"), ("cancelled", "1")])
                 .await?;
+
+            self.publish_job_status(&mut conn, job_id).await
+        })
+        .await?;
+
+        info!("Cancelled job {}", job_id);
+        Ok(())
+    }
+
+    /// Remember where to POST the job's summary once it completes
+    pub async fn set_job_callback_url(&self, job_id: &str, url: &str) -> Result<()> {
+        let key = &self.key(&format!("job:{}", job_id));
+
+        self.with_retries(|mut conn| async move { conn.hset(key, "callback_url", url).await })
+            .await
+    }
+
+    pub async fn get_job_callback_url(&self, job_id: &str) -> Result<Option<String>> {
+        let key = &self.key(&format!("job:{}", job_id));
+
+        self.with_retries(|mut conn| async move { conn.hget(key, "callback_url").await })
+            .await
+    }
+
+    /// Publish a job's full status (not just the fields that changed) to its
+    /// progress channel
+    async fn publish_job_status(
+        &self,
+        conn: &mut ConnectionManager,
+        job_id: &str,
+    ) -> RedisResult<()> {
+        let key = self.key(&format!("job:{}", job_id));
+        let data: HashMap<String, String> = conn.hgetall(&key).await?;
+
+        let json = serde_json::to_string(&JobStatus::from_hash(&data)).map_err(|e| {
+            RedisError::from((
+                ErrorKind::ClientError,
+                "Failed to encode status",
+                e.to_string(),
+            ))
+        })?;
+        conn.publish(self.progress_channel(job_id), json).await
+    }
+
+    /// Subscribe to the statuses published whenever a job's status changes
+    pub async fn subscribe_job_progress(
+        &self,
+        job_id: &str,
+    ) -> Result<impl Stream<Item = JobStatus>> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.subscribe(self.progress_channel(job_id)).await?;
+
+        Ok(pubsub.into_on_message().filter_map(|msg| async move {
+            let json: String = msg.get_payload().ok()?;
+            serde_json::from_str(&json).ok()
+        }))
+    }
+
+    pub async fn get_job_status(&self, job_id: &str) -> Result<Option<JobStatus>> {
+        let key = &self.key(&format!("job:{}", job_id));
+        let data: HashMap<String, String> = self
+            .with_retries(|mut conn| async move { conn.hgetall(key).await })
+            .await?;
+
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(JobStatus::from_hash(&data)))
+    }
+
+    pub async fn set_transcription_result(
+        &self,
+        recording_id: &str,
+        result: &TranscriptionStatus,
+    ) -> Result<()> {
+        let key = &self.key(&format!("result:{}", recording_id));
+
+        let segments = result
+            .segments
+            .as_ref()
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| TranscribeError::Internal(format!("Failed to encode segments: {}", e)))?;
+
+        let mut fields = vec![("status", result.status.clone())];
+        if let Some(ref text) = result.text {
+            fields.push(("text", text.clone()));
+        }
+        if let Some(duration) = result.duration {
+            fields.push(("duration", duration.to_string()));
+        }
+        if let Some(ref error) = result.error {
+            fields.push(("error", error.clone()));
+        }
+        if let Some(ref srt) = result.srt {
+            fields.push(("srt", srt.clone()));
+        }
+        if let Some(ref vtt) = result.vtt {
+            fields.push(("vtt", vtt.clone()));
+        }
+        if let Some(segments) = segments {
+            fields.push(("segments", segments));
+        }
+        if let Some(ref metadata) = result.metadata {
+            fields.push(("metadata", metadata.to_string()));
+        }
+        if let Some(ref object_key) = result.result_ref {
+            fields.push(("result_ref", object_key.clone()));
+        }
+        let fields = &fields;
+
+        // Replace the whole hash, so nothing from an earlier run (an old
+        // error, an old transcript, a spilled result) outlives it
+        self.with_retries(|mut conn| async move {
+            redis::pipe()
+                .atomic()
+                .del(key)
+                .ignore()
+                .hset_multiple(key, fields)
+                .ignore()
+                .expire(key, self.result_ttl_secs)
+                .ignore()
+                .query_async(&mut conn)
+                .await
+        })
+        .await
+    }
+
+    pub async fn get_transcription_result(
+        &self,
+        recording_id: &str,
+    ) -> Result<Option<TranscriptionStatus>> {
+        let key = &self.key(&format!("result:{}", recording_id));
+        let data: HashMap<String, String> = self
+            .with_retries(|mut conn| async move { conn.hgetall(key).await })
+            .await?;
+
+        if data.is_empty() {
+            return Ok(None);
         }
 
-        if let Some(total) = status.total {
-            conn.hset::<_, _, _, ()>(&key, "total", total.to_string())
+        Ok(Some(TranscriptionStatus {
+            status: data.get("status").cloned().unwrap_or_default(),
+            text: data.get("text").cloned(),
+            duration: data.get("duration").and_then(|s| s.parse().ok()),
+            error: data.get("error").cloned(),
+            srt: data.get("srt").cloned(),
+            vtt: data.get("vtt").cloned(),
+            segments: data
+                .get("segments")
+                .and_then(|json| serde_json::from_str(json).ok()),
+            metadata: data
+                .get("metadata")
+                .and_then(|json| serde_json::from_str(json).ok()),
+            result_ref: data.get("result_ref").cloned(),
+        }))
+    }
+
+    /// Remember where a recording's result JSON was written in the bucket
+    pub async fn set_result_artifact(&self, recording_id: &str, object_key: &str) -> Result<()> {
+        let key = &self.key(&format!("result:{}", recording_id));
+
+        self.with_retries(|mut conn| async move {
+            conn.hset::<_, _, _, ()>(key, "artifact", object_key)
                 .await?;
+            conn.expire(key, self.result_ttl_secs).await
+        })
+        .await
+    }
+
+    pub async fn get_result_artifact(&self, recording_id: &str) -> Result<Option<String>> {
+        let key = &self.key(&format!("result:{}", recording_id));
+
+        self.with_retries(|mut conn| async move { conn.hget(key, "artifact").await })
+            .await
+    }
+
+    /// Bucket objects holding a recording's result: the `store_result_key`
+    /// artifact and a result spilled there for size
+    pub async fn get_result_objects(&self, recording_id: &str) -> Result<Vec<String>> {
+        let key = &self.key(&format!("result:{}", recording_id));
+        let objects: Vec<Option<String>> = self
+            .with_retries(
+                |mut conn| async move { conn.hget(key, &["artifact", "result_ref"]).await },
+            )
+            .await?;
+
+        Ok(objects.into_iter().flatten().collect())
+    }
+
+    /// Delete a recording's result, and its callback record whose body holds
+    /// the transcript too. False when there was no result.
+    pub async fn delete_transcription_result(&self, recording_id: &str) -> Result<bool> {
+        let result_key = &self.key(&format!("result:{}", recording_id));
+        let callback_key = &self.key(&format!("callback:{}", recording_id));
+        let due_key = &self.key(CALLBACK_DUE_KEY);
+
+        let (deleted,): (u32,) = self
+            .with_retries(|mut conn| async move {
+                redis::pipe()
+                    .atomic()
+                    .del(result_key)
+                    .del(callback_key)
+                    .ignore()
+                    .zrem(due_key, recording_id)
+                    .ignore()
+                    .query_async(&mut conn)
+                    .await
+            })
+            .await?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Recording IDs of the stored results whose ID starts with `prefix`
+    pub async fn scan_result_ids(&self, prefix: &str) -> Result<Vec<String>> {
+        let key_prefix = self.key("result:");
+        let pattern = &format!("{}*", escape_glob(&format!("{}{}", key_prefix, prefix)));
+
+        let keys: Vec<String> = self
+            .with_retries(
+                |mut conn| async move { Ok(conn.scan_match(pattern).await?.collect().await) },
+            )
+            .await?;
+
+        Ok(keys
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&key_prefix).map(str::to_string))
+            .collect())
+    }
+
+    /// Claim an idempotency key for a new request. Returns `None` if this call
+    /// claimed it, otherwise what is stored: the first request's response, or
+    /// an empty string while that request is still running.
+    pub async fn claim_idempotency_key(
+        &self,
+        scope: &str,
+        key: &str,
+        ttl_secs: u64,
+    ) -> Result<Option<String>> {
+        let mut conn = self.connection().await?;
+
+        let key = self.key(&format!("idem:{}:{}", scope, key));
+        // The pending marker outlives a crashed request only briefly
+        let claimed: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg("")
+            .arg("NX")
+            .arg("EX")
+            .arg(ttl_secs.min(IDEMPOTENCY_PENDING_TTL_SECS))
+            .query_async(&mut conn)
+            .await?;
+        if claimed.is_some() {
+            return Ok(None);
         }
 
-        // Set expiration (24 hours)
-        conn.expire::<_, ()>(&key, 86400).await?;
+        let stored: Option<String> = conn.get(&key).await?;
+        Ok(Some(stored.unwrap_or_default()))
+    }
 
-        info!("Updated job {} status: {:?}", job_id, status);
-        Ok(())
+    /// Store the response replayed for repeats of an idempotency key
+    pub async fn complete_idempotency_key(
+        &self,
+        scope: &str,
+        key: &str,
+        response: &str,
+        ttl_secs: u64,
+    ) -> Result<()> {
+        let key = &self.key(&format!("idem:{}:{}", scope, key));
+
+        self.with_retries(|mut conn| async move { conn.set_ex(key, response, ttl_secs).await })
+            .await
     }
 
-    pub async fn get_job_status(&self, job_id: &str) -> Result<Option<JobStatus>> {
-        let mut conn = self
-            .client
-            .get_multiplexed_async_connection()
+    /// Forget an idempotency key whose request failed, so it can be retried
+    pub async fn release_idempotency_key(&self, scope: &str, key: &str) -> Result<()> {
+        let key = &self.key(&format!("idem:{}:{}", scope, key));
+
+        self.with_retries(|mut conn| async move { conn.del(key).await })
+            .await
+    }
+
+    pub async fn get_cached_result(&self, key: &str) -> Result<Option<TranscriptionResult>> {
+        let key = &self.key(key);
+        let data: Option<String> = self
+            .with_retries(|mut conn| async move { conn.get(key).await })
+            .await?;
+
+        data.map(|json| {
+            serde_json::from_str(&json)
+                .map_err(|e| TranscribeError::Queue(format!("Corrupt cached result: {}", e)))
+        })
+        .transpose()
+    }
+
+    pub async fn set_cached_result(
+        &self,
+        key: &str,
+        result: &TranscriptionResult,
+        ttl_secs: u64,
+    ) -> Result<()> {
+        let key = &self.key(key);
+        let json = &serde_json::to_string(result)
+            .map_err(|e| TranscribeError::Internal(format!("Failed to encode result: {}", e)))?;
+
+        self.with_retries(|mut conn| async move { conn.set_ex(key, json, ttl_secs).await })
             .await
-            .context("Failed to get Redis connection")?;
+    }
+
+    /// Record that a batch job finished processing a recording
+    pub async fn add_job_result(&self, job_id: &str, recording_id: &str) -> Result<()> {
+        let mut conn = self.connection().await?;
+
+        let key = self.key(&format!("job:{}:results", job_id));
+
+        conn.rpush::<_, _, ()>(&key, recording_id).await?;
+
+        // Keep the list as long as the results it points to
+        conn.expire::<_, ()>(&key, self.result_ttl_secs).await?;
+
+        Ok(())
+    }
+
+    /// Results for every recording a batch job has finished, in completion order
+    pub async fn get_job_results(&self, job_id: &str) -> Result<Vec<JobResult>> {
+        let key = &self.key(&format!("job:{}:results", job_id));
+        let recording_ids: Vec<String> = self
+            .with_retries(|mut conn| async move { conn.lrange(key, 0, -1).await })
+            .await?;
+
+        let mut results = Vec::with_capacity(recording_ids.len());
+        for recording_id in recording_ids {
+            // Skip results that have expired independently of the list
+            if let Some(result) = self.get_transcription_result(&recording_id).await? {
+                results.push(JobResult {
+                    recording_id,
+                    result,
+                });
+            }
+        }
+
+        Ok(results)
+    }
+
+    pub async fn push_dead_letter(&self, entry: &DeadLetter) -> Result<()> {
+        let mut conn = self.connection().await?;
+
+        let json = serde_json::to_string(entry)
+            .map_err(|e| TranscribeError::Internal(format!("Failed to encode entry: {}", e)))?;
+        conn.rpush::<_, _, ()>(self.key(DEAD_LETTER_KEY), json)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let key = &self.key(DEAD_LETTER_KEY);
+        let entries: Vec<String> = self
+            .with_retries(|mut conn| async move { conn.lrange(key, 0, -1).await })
+            .await?;
+
+        Ok(parse_dead_letters(entries))
+    }
+
+    /// Up to `limit` dead-letter entries, oldest first, along with their
+    /// stored form for `remove_dead_letters`. Nothing is removed, so a retry
+    /// that fails to start loses nothing.
+    pub async fn peek_dead_letters(&self, limit: usize) -> Result<(Vec<DeadLetter>, Vec<String>)> {
+        let key = &self.key(DEAD_LETTER_KEY);
+        let stop = limit as isize - 1;
+        let entries: Vec<String> = self
+            .with_retries(|mut conn| async move { conn.lrange(key, 0, stop).await })
+            .await?;
+
+        Ok((parse_dead_letters(entries.clone()), entries))
+    }
+
+    /// Remove entries read by `peek_dead_letters`, leaving any pushed since
+    pub async fn remove_dead_letters(&self, entries: &[String]) -> Result<()> {
+        let mut conn = self.connection().await?;
+
+        let key = self.key(DEAD_LETTER_KEY);
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        for entry in entries {
+            pipe.lrem(&key, 1, entry).ignore();
+        }
+        pipe.query_async::<()>(&mut conn).await?;
+
+        Ok(())
+    }
 
-        let key = format!("transcribe:job:{}", job_id);
-        let data: std::collections::HashMap<String, String> =
-            conn.hgetall(&key).await.context("Failed to get job status")?;
+    /// Record a callback as pending and due now, replacing any earlier
+    /// delivery for the recording
+    pub async fn schedule_callback(&self, recording_id: &str, url: &str, body: &str) -> Result<()> {
+        let key = &self.key(&format!("callback:{}", recording_id));
+        let now = unix_now();
+
+        self.with_retries(|mut conn| async move {
+            redis::pipe()
+                .atomic()
+                .del(key)
+                .ignore()
+                .hset_multiple(
+                    key,
+                    &[
+                        ("url", url),
+                        ("body", body),
+                        ("status", "pending"),
+                        ("attempts", "0"),
+                        ("updated_at", &now.as_secs().to_string()),
+                    ],
+                )
+                .ignore()
+                .expire(key, self.result_ttl_secs)
+                .ignore()
+                .zadd(self.key(CALLBACK_DUE_KEY), recording_id, now.as_secs_f64())
+                .ignore()
+                .query_async(&mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Take up to `limit` callbacks that are due, leasing them for `lease` so
+    /// no other pod attempts them meanwhile. A callback whose attempt is never
+    /// recorded (the pod died) becomes due again when the lease runs out.
+    pub async fn claim_due_callbacks(&self, lease: Duration, limit: usize) -> Result<Vec<String>> {
+        let mut conn = self.connection().await?;
+
+        let now = unix_now();
+        let due: Vec<String> = redis::Script::new(CLAIM_DUE_SCRIPT)
+            .key(self.key(CALLBACK_DUE_KEY))
+            .arg(now.as_secs_f64())
+            .arg((now + lease).as_secs_f64())
+            .arg(limit)
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok(due)
+    }
+
+    pub async fn get_callback(&self, recording_id: &str) -> Result<Option<CallbackDelivery>> {
+        let key = &self.key(&format!("callback:{}", recording_id));
+        let data: HashMap<String, String> = self
+            .with_retries(|mut conn| async move { conn.hgetall(key).await })
+            .await?;
 
         if data.is_empty() {
             return Ok(None);
         }
 
-        Ok(Some(JobStatus {
+        Ok(Some(CallbackDelivery {
+            recording_id: recording_id.to_string(),
+            url: data.get("url").cloned().unwrap_or_default(),
             status: data.get("status").cloned().unwrap_or_default(),
-            current: data.get("current").and_then(|s| s.parse().ok()),
-            total: data.get("total").and_then(|s| s.parse().ok()),
+            attempts: data
+                .get("attempts")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+            last_error: data.get("last_error").cloned(),
+            updated_at: data
+                .get("updated_at")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
         }))
     }
 
-    pub async fn set_transcription_result(
+    /// The JSON body to deliver for a recording's callback
+    pub async fn get_callback_body(&self, recording_id: &str) -> Result<Option<String>> {
+        let key = &self.key(&format!("callback:{}", recording_id));
+
+        self.with_retries(|mut conn| async move { conn.hget(key, "body").await })
+            .await
+    }
+
+    /// Record the outcome of a delivery attempt. With `retry_in` the callback
+    /// stays pending and becomes due again after that delay; otherwise it is
+    /// settled with `status`.
+    pub async fn record_callback_attempt(
         &self,
         recording_id: &str,
-        result: &TranscriptionStatus,
+        status: &str,
+        error: Option<&str>,
+        retry_in: Option<Duration>,
     ) -> Result<()> {
-        let mut conn = self
-            .client
-            .get_multiplexed_async_connection()
+        let mut conn = self.connection().await?;
+
+        let key = self.key(&format!("callback:{}", recording_id));
+        let due_key = self.key(CALLBACK_DUE_KEY);
+        let now = unix_now();
+
+        let mut pipe = redis::pipe();
+        pipe.atomic()
+            .hincr(&key, "attempts", 1)
+            .ignore()
+            .hset(&key, "status", status)
+            .ignore()
+            .hset(&key, "updated_at", now.as_secs())
+            .ignore();
+        match error {
+            Some(error) => pipe.hset(&key, "last_error", error).ignore(),
+            None => pipe.hdel(&key, "last_error").ignore(),
+        };
+        match retry_in {
+            Some(delay) => pipe
+                .zadd(&due_key, recording_id, (now + delay).as_secs_f64())
+                .ignore(),
+            None => pipe.zrem(&due_key, recording_id).ignore(),
+        };
+        pipe.query_async::<()>(&mut conn).await?;
+
+        Ok(())
+    }
+
+    /// Stop attempting a callback whose record has expired
+    pub async fn drop_callback(&self, recording_id: &str) -> Result<()> {
+        let key = &self.key(CALLBACK_DUE_KEY);
+
+        self.with_retries(|mut conn| async move { conn.zrem(key, recording_id).await })
             .await
-            .context("Failed to get Redis connection")?;
+    }
 
-        let key = format!("transcribe:result:{}", recording_id);
+    /// Add an item to the work queue shared by every pod. Higher priorities
+    /// pop first; items of equal priority pop in the order they were enqueued.
+    pub async fn enqueue(&self, item: &QueuedItem, priority: u8) -> Result<()> {
+        let json = &serde_json::to_string(item)
+            .map_err(|e| TranscribeError::Internal(format!("Failed to encode item: {}", e)))?;
 
-        conn.hset::<_, _, _, ()>(&key, "status", &result.status)
-            .await?;
+        // Repeating this at most skips a sequence number: the item is the set
+        // member, so adding it again only moves it
+        self.with_retries(|mut conn| async move {
+            // Lower scores pop first: priority dominates, the sequence keeps FIFO order
+            let seq: u64 = conn.incr(self.key(WORK_QUEUE_SEQ_KEY), 1).await?;
+            let score = (u8::MAX - priority) as f64 * PRIORITY_STRIDE + seq as f64;
 
-        if let Some(ref text) = result.text {
-            conn.hset::<_, _, _, ()>(&key, "text", text).await?;
+            conn.zadd(self.key(WORK_QUEUE_KEY), json, score).await
+        })
+        .await
+    }
+
+    /// Number of items waiting in the work queue
+    pub async fn queue_len(&self) -> Result<u64> {
+        self.with_retries(|mut conn| async move { conn.zcard(self.key(WORK_QUEUE_KEY)).await })
+            .await
+    }
+
+    /// Take the next item, waiting up to `timeout` for one to arrive. The
+    /// item moves to the processing set until `ack_item`; if its heartbeat
+    /// stops (the pod died), `requeue_stale_items` puts it back.
+    pub async fn dequeue(&self, timeout: Duration) -> Result<Option<ClaimedItem>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let mut conn = self.connection().await?;
+            let member: Option<String> = redis::Script::new(CLAIM_NEXT_SCRIPT)
+                .key(self.key(WORK_QUEUE_KEY))
+                .key(self.key(WORK_PROCESSING_KEY))
+                .arg(unix_now().as_secs())
+                .invoke_async(&mut conn)
+                .await?;
+
+            if let Some(member) = member {
+                let item = serde_json::from_str(&member)
+                    .map_err(|e| TranscribeError::Queue(format!("Corrupt queued item: {}", e)))?;
+                return Ok(Some(ClaimedItem { item, member }));
+            }
+            if Instant::now() >= deadline {
+                return Ok(None);
+            }
+            tokio::time::sleep(DEQUEUE_POLL_INTERVAL).await;
         }
+    }
 
-        if let Some(duration) = result.duration {
-            conn.hset::<_, _, _, ()>(&key, "duration", duration.to_string())
+    /// Record that a claimed item is still being worked on
+    pub async fn heartbeat_item(&self, claimed: &ClaimedItem) -> Result<()> {
+        let member = &claimed.member;
+
+        self.with_retries(|mut conn| async move {
+            // XX: an item already acknowledged or requeued isn't brought back
+            redis::cmd("ZADD")
+                .arg(self.key(WORK_PROCESSING_KEY))
+                .arg("XX")
+                .arg(unix_now().as_secs())
+                .arg(member)
+                .query_async(&mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Drop a claimed item from the processing set once it has been handled
+    pub async fn ack_item(&self, claimed: &ClaimedItem) -> Result<()> {
+        let member = &claimed.member;
+
+        self.with_retries(|mut conn| async move {
+            conn.zrem(self.key(WORK_PROCESSING_KEY), member).await
+        })
+        .await
+    }
+
+    /// Return items whose heartbeat is older than `stale_after` to the work
+    /// queue, at their original priority. Their jobs then resume where they
+    /// stopped: items already completed aren't repeated.
+    pub async fn requeue_stale_items(&self, stale_after: Duration) -> Result<usize> {
+        let cutoff = unix_now().saturating_sub(stale_after).as_secs();
+        let processing = &self.key(WORK_PROCESSING_KEY);
+        let stale: Vec<String> = self
+            .with_retries(
+                |mut conn| async move { conn.zrangebyscore(processing, "-inf", cutoff).await },
+            )
+            .await?;
+
+        let mut requeued = 0;
+        for member in stale {
+            let priority = match serde_json::from_str::<QueuedItem>(&member) {
+                Ok(item) => item.request.priority,
+                Err(e) => {
+                    warn!("Dropping unreadable processing entry: {}", e);
+                    self.with_retries(|mut conn| {
+                        let member = member.clone();
+                        async move { conn.zrem::<_, _, ()>(processing, member).await }
+                    })
+                    .await?;
+                    continue;
+                }
+            };
+
+            // Moved only if still stale, so a sweep racing another pod's (or a
+            // late heartbeat) can't queue an item twice
+            let mut conn = self.connection().await?;
+            let moved: u8 = redis::Script::new(REQUEUE_SCRIPT)
+                .key(processing)
+                .key(self.key(WORK_QUEUE_KEY))
+                .key(self.key(WORK_QUEUE_SEQ_KEY))
+                .arg(&member)
+                .arg(cutoff)
+                .arg(u8::MAX - priority)
+                .arg(PRIORITY_STRIDE)
+                .invoke_async(&mut conn)
                 .await?;
+            requeued += moved as usize;
         }
 
-        if let Some(ref error) = result.error {
-            conn.hset::<_, _, _, ()>(&key, "error", error).await?;
+        Ok(requeued)
+    }
+
+    /// Take a request token from a client's bucket. Returns `None` when the
+    /// request may proceed, or how long to wait before the next token.
+    pub async fn take_rate_limit_token(
+        &self,
+        client: &str,
+        limit: RateLimit,
+    ) -> Result<Option<Duration>> {
+        let mut conn = self.connection().await?;
+
+        let key = self.key(&format!("ratelimit:{}", client));
+        let refill_per_ms = limit.per_minute as f64 / 60_000.0;
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+
+        let (allowed, wait_ms): (u8, u64) = redis::Script::new(TOKEN_BUCKET_SCRIPT)
+            .key(&key)
+            .arg(limit.burst.max(1))
+            .arg(refill_per_ms)
+            .arg(now_ms)
+            .invoke_async(&mut conn)
+            .await?;
+
+        Ok((allowed == 0).then(|| Duration::from_millis(wait_ms)))
+    }
+
+    fn progress_channel(&self, job_id: &str) -> String {
+        self.key(&format!("job:{}:progress", job_id))
+    }
+
+    /// Count a finished item and return how many of the job's items are
+    /// done, and how many of those failed
+    pub async fn complete_job_item(&self, job_id: &str, failed: bool) -> Result<(u32, u32)> {
+        let mut conn = self.connection().await?;
+
+        let key = self.key(&format!("job:{}", job_id));
+        // Failures are counted first, so whoever sees the last item done
+        // also sees every failure
+        if failed {
+            conn.hincr::<_, _, _, ()>(&key, "failed", 1).await?;
         }
+        let current: u32 = conn.hincr(&key, "current", 1).await?;
+        let failures: Option<u32> = conn.hget(&key, "failed").await?;
 
-        // Set expiration (7 days)
-        conn.expire::<_, ()>(&key, 604800).await?;
+        self.publish_job_status(&mut conn, job_id).await?;
 
-        Ok(())
+        Ok((current, failures.unwrap_or_default()))
+    }
+}
+
+fn unix_now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// Match `value` literally in a `SCAN MATCH` pattern
+fn escape_glob(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Connection failures are worth retrying; command errors are not
+fn is_retryable(error: &RedisError) -> bool {
+    error.is_io_error() || error.kind() == ErrorKind::BusyLoadingError
+}
+
+/// Token bucket refill and take, atomically. Returns `{allowed, wait_ms}`.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local refill_per_ms = tonumber(ARGV[2])
+local now = tonumber(ARGV[3])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(bucket[1]) or capacity
+local ts = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * refill_per_ms)
+local allowed = 0
+local wait_ms = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+else
+    wait_ms = math.ceil((1 - tokens) / refill_per_ms)
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], math.ceil(capacity / refill_per_ms) + 1000)
+return {allowed, wait_ms}
+"#;
+
+/// Set job KEYS[1]'s status to ARGV[1] unless it was cancelled, and its
+/// current and total to ARGV[2] and ARGV[3] when not empty, then expire it in
+/// ARGV[4] seconds, atomically. Returns 1 when the status was set.
+const SET_JOB_STATUS_SCRIPT: &str = r#"
+local cancelled = redis.call('HEXISTS', KEYS[1], 'cancelled') == 1
+if not cancelled then
+    redis.call('HSET', KEYS[1], 'status', ARGV[1])
+end
+if ARGV[2] ~= '' then
+    redis.call('HSET', KEYS[1], 'current', ARGV[2])
+end
+if ARGV[3] ~= '' then
+    redis.call('HSET', KEYS[1], 'total', ARGV[3])
+end
+redis.call('EXPIRE', KEYS[1], ARGV[4])
+if cancelled then
+    return 0
+end
+return 1
+"#;
+
+/// Take up to ARGV[3] members scored at most ARGV[1] and rescore them to
+/// ARGV[2], atomically. Returns the members taken.
+const CLAIM_DUE_SCRIPT: &str = r#"
+local due = redis.call('ZRANGEBYSCORE', KEYS[1], '-inf', ARGV[1], 'LIMIT', 0, ARGV[3])
+for _, member in ipairs(due) do
+    redis.call('ZADD', KEYS[1], ARGV[2], member)
+end
+return due
+"#;
+
+/// Pop the lowest-scored item of KEYS[1] into KEYS[2], scored ARGV[1] (its
+/// first heartbeat), atomically. Returns the item, or nil when none is queued.
+const CLAIM_NEXT_SCRIPT: &str = r#"
+local popped = redis.call('ZPOPMIN', KEYS[1])
+if #popped == 0 then
+    return false
+end
+redis.call('ZADD', KEYS[2], ARGV[1], popped[1])
+return popped[1]
+"#;
+
+/// Move ARGV[1] from KEYS[1] back to the queue KEYS[2] if its heartbeat is
+/// still at most ARGV[2], scored by priority rank ARGV[3] and stride ARGV[4]
+/// like `enqueue`. Returns 1 when it was moved.
+const REQUEUE_SCRIPT: &str = r#"
+local heartbeat = redis.call('ZSCORE', KEYS[1], ARGV[1])
+if not heartbeat or tonumber(heartbeat) > tonumber(ARGV[2]) then
+    return 0
+end
+redis.call('ZREM', KEYS[1], ARGV[1])
+local seq = redis.call('INCR', KEYS[3])
+redis.call('ZADD', KEYS[2], tonumber(ARGV[3]) * tonumber(ARGV[4]) + seq, ARGV[1])
+return 1
+"#;
+
+/// Decode stored entries, skipping any that no longer parse
+fn parse_dead_letters(entries: Vec<String>) -> Vec<DeadLetter> {
+    entries
+        .iter()
+        .filter_map(|json| {
+            serde_json::from_str(json)
+                .inspect_err(|e| warn!("Skipping unreadable dead-letter entry: {}", e))
+                .ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(status: &str) -> TranscriptionStatus {
+        TranscriptionStatus {
+            status: status.to_string(),
+            text: None,
+            duration: None,
+            error: None,
+            srt: None,
+            vtt: None,
+            segments: None,
+            metadata: None,
+            result_ref: None,
+        }
+    }
+
+    /// Needs a Redis server; skipped unless `REDIS_URL` is set
+    #[tokio::test]
+    async fn a_success_replaces_an_earlier_failure() {
+        if std::env::var("REDIS_URL").is_err() {
+            return;
+        }
+        let mut config = Config::from_env();
+        config.redis_key_prefix = format!("test-{}", std::process::id());
+        let queue = Queue::new(&config).unwrap();
+
+        let failed = TranscriptionStatus {
+            error: Some("ffmpeg failed".to_string()),
+            ..status("failed")
+        };
+        queue
+            .set_transcription_result("rec", &failed)
+            .await
+            .unwrap();
+
+        let completed = TranscriptionStatus {
+            text: Some("hello".to_string()),
+            duration: Some(1.0),
+            ..status("completed")
+        };
+        queue
+            .set_transcription_result("rec", &completed)
+            .await
+            .unwrap();
+
+        let stored = queue
+            .get_transcription_result("rec")
+            .await
+            .unwrap()
+            .unwrap();
+        queue.delete_transcription_result("rec").await.unwrap();
+
+        assert_eq!(stored.status, "completed");
+        assert_eq!(stored.text.as_deref(), Some("hello"));
+        assert_eq!(stored.error, None);
     }
 }
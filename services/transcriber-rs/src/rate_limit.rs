@@ -0,0 +1,58 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use sha2::{Digest, Sha256};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::auth::PUBLIC_PATHS;
+use crate::error::{Result, TranscribeError};
+use crate::handlers::AppState;
+
+/// Requests-per-minute token bucket shared by every pod through Redis
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub per_minute: u32,
+    /// Requests a client can make at once after being idle
+    pub burst: u32,
+}
+
+/// Limit each client (by API key, or by peer IP without one) to the configured
+/// rate. Redis failures let requests through rather than taking the API down.
+pub async fn limit_requests(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Result<Response> {
+    let Some(limit) = state.rate_limit else {
+        return Ok(next.run(request).await);
+    };
+    if PUBLIC_PATHS.contains(&request.uri().path()) {
+        return Ok(next.run(request).await);
+    }
+
+    // Keys are hashed so raw API keys never end up in Redis
+    let client = match request
+        .headers()
+        .get("x-api-key")
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(key) => format!("key:{}", hex::encode(&Sha256::digest(key.as_bytes())[..8])),
+        None => format!("ip:{}", peer.ip()),
+    };
+
+    match state.queue.take_rate_limit_token(&client, limit).await {
+        Ok(None) => {}
+        Ok(Some(retry_after)) => {
+            warn!("Rate limit exceeded for {}", client);
+            return Err(TranscribeError::RateLimited(retry_after.as_secs().max(1)));
+        }
+        Err(e) => warn!("Rate limiter unavailable, allowing request: {}", e),
+    }
+
+    Ok(next.run(request).await)
+}
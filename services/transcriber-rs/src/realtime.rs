@@ -0,0 +1,132 @@
+use crate::transcriber::Segment;
+
+/// Decode the buffered audio again once this much new audio has arrived
+const PARTIAL_INTERVAL_SECS: f64 = 1.0;
+
+/// Once the buffer reaches this length, words before the tail are finalised
+/// and their audio dropped, so each decode stays bounded
+const MAX_WINDOW_SECS: f64 = 15.0;
+
+/// Words ending within this distance of the buffer end may still change as
+/// more audio arrives, so they are never finalised early
+const UNSTABLE_TAIL_SECS: f64 = 2.0;
+
+/// Words finalised by a decode, and the still-tentative words after them
+#[derive(Debug, Default)]
+pub struct LiveUpdate {
+    pub finals: Vec<Segment>,
+    pub partials: Vec<Segment>,
+}
+
+/// Buffers incoming little-endian 16-bit mono PCM and decides when to decode
+/// and which words are stable enough to report as final
+pub struct LiveSession {
+    sample_rate: u32,
+    /// Audio not yet finalised, starting at `offset` seconds into the stream
+    buffer: Vec<f32>,
+    offset: f64,
+    /// Samples received since the last decode
+    undecoded: usize,
+    /// Odd byte left over when a frame splits a sample
+    leftover: Option<u8>,
+}
+
+impl LiveSession {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            buffer: Vec::new(),
+            offset: 0.0,
+            undecoded: 0,
+            leftover: None,
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Append a frame of s16le PCM; frames need not align to sample boundaries
+    pub fn push_pcm(&mut self, frame: &[u8]) {
+        let mut bytes = frame;
+        if let Some(low) = self.leftover.take() {
+            let Some((&high, rest)) = bytes.split_first() else {
+                self.leftover = Some(low);
+                return;
+            };
+            self.push_sample(i16::from_le_bytes([low, high]));
+            bytes = rest;
+        }
+
+        let mut samples = bytes.chunks_exact(2);
+        for pair in &mut samples {
+            self.push_sample(i16::from_le_bytes([pair[0], pair[1]]));
+        }
+        self.leftover = samples.remainder().first().copied();
+    }
+
+    fn push_sample(&mut self, sample: i16) {
+        self.buffer.push(sample as f32 / i16::MAX as f32);
+        self.undecoded += 1;
+    }
+
+    /// Whether enough new audio has arrived to be worth decoding
+    pub fn is_due(&self) -> bool {
+        self.undecoded as f64 >= PARTIAL_INTERVAL_SECS * self.sample_rate as f64
+    }
+
+    /// Whether any audio has arrived since the last decode
+    pub fn has_undecoded(&self) -> bool {
+        self.undecoded > 0
+    }
+
+    /// The audio to decode next; resets the due counter
+    pub fn take_window(&mut self) -> Vec<f32> {
+        self.undecoded = 0;
+        self.buffer.clone()
+    }
+
+    /// Split the words decoded from `take_window` into final and partial,
+    /// dropping audio that is now finalised. With `finish`, every word is final.
+    pub fn apply(&mut self, words: Vec<Segment>, finish: bool) -> LiveUpdate {
+        let offset = self.offset;
+        let words = words.into_iter().map(|mut word| {
+            word.start += offset;
+            word.end += offset;
+            word
+        });
+
+        let buffer_secs = self.buffer.len() as f64 / self.sample_rate as f64;
+        if finish {
+            self.advance(buffer_secs);
+            return LiveUpdate {
+                finals: words.collect(),
+                partials: Vec::new(),
+            };
+        }
+        if buffer_secs < MAX_WINDOW_SECS {
+            return LiveUpdate {
+                finals: Vec::new(),
+                partials: words.collect(),
+            };
+        }
+
+        let stable_until = offset + buffer_secs - UNSTABLE_TAIL_SECS;
+        let (finals, partials): (Vec<Segment>, Vec<Segment>) =
+            words.partition(|word| word.end <= stable_until);
+
+        // Cut right after the last final word; with none, the audio before the
+        // tail held no speech
+        let cut = finals.last().map_or(stable_until, |word| word.end);
+        self.advance(cut - offset);
+
+        LiveUpdate { finals, partials }
+    }
+
+    /// Drop `secs` of audio from the front of the buffer
+    fn advance(&mut self, secs: f64) {
+        let samples = ((secs * self.sample_rate as f64) as usize).min(self.buffer.len());
+        self.buffer.drain(..samples);
+        self.offset += samples as f64 / self.sample_rate as f64;
+    }
+}
@@ -0,0 +1,238 @@
+use crate::transcriber::Segment;
+
+/// Group word-level segments into larger segments, starting a new one
+/// whenever the silence between consecutive words reaches `gap_secs`
+/// or the speaker or language changes.
+pub fn group_by_gap(words: &[Segment], gap_secs: f64) -> Vec<Segment> {
+    let mut groups: Vec<Vec<&Segment>> = Vec::new();
+
+    for word in words {
+        match groups.last_mut() {
+            Some(current)
+                if current.last().is_some_and(|last| {
+                    word.start - last.end < gap_secs
+                        && word.speaker == last.speaker
+                        && word.language == last.language
+                }) =>
+            {
+                current.push(word)
+            }
+            _ => groups.push(vec![word]),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|group| merge_words(&group))
+        .collect()
+}
+
+/// Group word-level segments into sentences, ending one after each word
+/// that ends with `.`, `?` or `!`, at a pause of at least `pause_secs` (for
+/// unpunctuated output), or when the speaker or language changes
+pub fn group_by_sentence(words: &[Segment], pause_secs: f64) -> Vec<Segment> {
+    let mut groups: Vec<Vec<&Segment>> = Vec::new();
+    let mut sentence_open = false;
+
+    for word in words {
+        match groups.last_mut() {
+            Some(current)
+                if sentence_open
+                    && current.last().is_some_and(|last| {
+                        word.speaker == last.speaker
+                            && word.language == last.language
+                            && word.start - last.end < pause_secs
+                    }) =>
+            {
+                current.push(word)
+            }
+            _ => groups.push(vec![word]),
+        }
+        sentence_open = !word.text.trim_end().ends_with(['.', '?', '!']);
+    }
+
+    groups
+        .into_iter()
+        .map(|group| merge_words(&group))
+        .collect()
+}
+
+/// Merge consecutive words into a single segment spanning all of them
+fn merge_words(words: &[&Segment]) -> Segment {
+    let mut text = String::new();
+    for word in words {
+        append_word(&mut text, &word.text);
+    }
+
+    // Average the confidences that are known; unknown stays unknown
+    let confidences: Vec<f32> = words.iter().filter_map(|w| w.confidence).collect();
+    let confidence = if confidences.is_empty() {
+        None
+    } else {
+        Some(confidences.iter().sum::<f32>() / confidences.len() as f32)
+    };
+
+    Segment {
+        start: words.first().map(|w| w.start).unwrap_or_default(),
+        end: words.iter().map(|w| w.end).fold(0.0, f64::max),
+        text,
+        confidence,
+        speaker: words.first().and_then(|w| w.speaker.clone()),
+        // A group is as loud as its loudest word
+        level_db: words.iter().filter_map(|w| w.level_db).reduce(f32::max),
+        language: words.first().and_then(|w| w.language.clone()),
+    }
+}
+
+/// Drop words that are fillers ("um", "uh"), ignoring case and punctuation
+pub fn remove_fillers(words: Vec<Segment>, fillers: &[&str]) -> Vec<Segment> {
+    words
+        .into_iter()
+        .filter(|word| {
+            let bare = word
+                .text
+                .trim()
+                .trim_matches(|c: char| c.is_ascii_punctuation())
+                .to_lowercase();
+            !fillers.contains(&bare.as_str())
+        })
+        .collect()
+}
+
+/// Drop words whose audio is quieter than `min_level_db`; words with no
+/// measured level are kept
+pub fn drop_silent(words: Vec<Segment>, min_level_db: f32) -> Vec<Segment> {
+    words
+        .into_iter()
+        .filter(|word| word.level_db.is_none_or(|level| level >= min_level_db))
+        .collect()
+}
+
+/// Restore sentence punctuation and capitalization in unpunctuated output:
+/// a sentence ends at a pause of at least `pause_secs`, a speaker change or
+/// the end of the transcript, and each sentence starts with a capital.
+/// Sentence ends the model already punctuated are kept.
+pub fn restore_punctuation(mut words: Vec<Segment>, pause_secs: f64) -> Vec<Segment> {
+    let mut sentence_start = true;
+    for i in 0..words.len() {
+        let ends_sentence = match words.get(i + 1) {
+            Some(next) => {
+                next.start - words[i].end >= pause_secs || next.speaker != words[i].speaker
+            }
+            None => true,
+        };
+
+        let word = &mut words[i].text;
+        if sentence_start && capitalize(word) {
+            sentence_start = false;
+        }
+        if word.trim_end().ends_with(['.', '?', '!']) {
+            sentence_start = true;
+        } else if ends_sentence {
+            let len = word.trim_end_matches([',', ';', ':', ' ']).len();
+            word.truncate(len);
+            word.push('.');
+            sentence_start = true;
+        }
+    }
+    words
+}
+
+/// Upper-case the first letter of a word; false when it has none (standalone
+/// punctuation)
+fn capitalize(word: &mut String) -> bool {
+    let Some((i, c)) = word.char_indices().find(|(_, c)| c.is_alphabetic()) else {
+        return false;
+    };
+    let upper: String = c.to_uppercase().collect();
+    word.replace_range(i..i + c.len_utf8(), &upper);
+    true
+}
+
+/// Join segment texts into running text
+pub fn join_text(segments: &[Segment]) -> String {
+    let mut text = String::new();
+    for segment in segments {
+        append_word(&mut text, &segment.text);
+    }
+    text
+}
+
+/// Trim a text and collapse each run of whitespace in it to one space. For
+/// `unspaced` languages (Japanese, Chinese, Thai, ...) runs are removed
+/// instead, except between ASCII characters so embedded Latin words and
+/// numbers (`iPhone 15`) stay apart.
+pub fn normalize_spacing(text: &str, unspaced: bool) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    let mut after_space = false;
+    for c in text.trim().chars() {
+        if c.is_whitespace() {
+            after_space = true;
+            continue;
+        }
+        if after_space {
+            let between_ascii = c.is_ascii() && normalized.ends_with(|p: char| p.is_ascii());
+            if !unspaced || between_ascii {
+                normalized.push(' ');
+            }
+            after_space = false;
+        }
+        normalized.push(c);
+    }
+    normalized
+}
+
+/// Append a word to a text, without a space before standalone punctuation
+fn append_word(text: &mut String, word: &str) {
+    let word = word.trim();
+    let is_standalone_punct = word.len() == 1
+        && word
+            .chars()
+            .all(|c| matches!(c, '.' | ',' | '!' | '?' | ';' | ':' | ')'));
+
+    if !text.is_empty() && !is_standalone_punct {
+        text.push(' ');
+    }
+    text.push_str(word);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, start: f64, end: f64) -> Segment {
+        Segment {
+            start,
+            end,
+            text: text.to_string(),
+            confidence: None,
+            speaker: None,
+            level_db: None,
+            language: None,
+        }
+    }
+
+    #[test]
+    fn group_by_gap_splits_at_the_configured_gap() {
+        // Pauses of 0.1s, 0.6s and 1.5s between the words
+        let words = [
+            word("one", 0.0, 0.4),
+            word("two", 0.5, 0.9),
+            word("three", 1.5, 1.9),
+            word("four", 3.4, 3.8),
+        ];
+
+        let tight: Vec<String> = group_by_gap(&words, 0.5)
+            .into_iter()
+            .map(|s| s.text)
+            .collect();
+        let loose: Vec<String> = group_by_gap(&words, 1.0)
+            .into_iter()
+            .map(|s| s.text)
+            .collect();
+
+        assert_eq!(tight, ["one two", "three", "four"]);
+        assert_eq!(loose, ["one two three", "four"]);
+        assert_ne!(tight, loose);
+    }
+}
@@ -0,0 +1,41 @@
+/// Length of one envelope frame
+const FRAME_SECS: f64 = 0.01;
+
+/// Level of silence in dBFS, reported for digital zero
+const FLOOR_DB: f32 = -120.0;
+
+/// Audio energy per 10 ms frame, kept so word levels can be measured after
+/// the samples themselves have been handed to the model
+pub struct LevelEnvelope {
+    /// Mean square of the samples in each frame
+    frames: Vec<f32>,
+}
+
+impl LevelEnvelope {
+    pub fn new(samples: &[f32], sample_rate: u32, channels: u16) -> Self {
+        let frame_len = ((sample_rate as f64 * FRAME_SECS) as usize).max(1) * channels as usize;
+        let frames = samples
+            .chunks(frame_len)
+            .map(|frame| frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32)
+            .collect();
+
+        Self { frames }
+    }
+
+    /// RMS level in dBFS of the audio between `start` and `end` seconds;
+    /// `None` when the span lies outside the audio
+    pub fn level_db(&self, start: f64, end: f64) -> Option<f32> {
+        let first = (start.max(0.0) / FRAME_SECS) as usize;
+        let last = ((end / FRAME_SECS).ceil() as usize)
+            .max(first + 1)
+            .min(self.frames.len());
+        let frames = self.frames.get(first..last).filter(|f| !f.is_empty())?;
+
+        let mean_square = frames.iter().sum::<f32>() / frames.len() as f32;
+        Some(if mean_square > 0.0 {
+            (10.0 * mean_square.log10()).max(FLOOR_DB)
+        } else {
+            FLOOR_DB
+        })
+    }
+}
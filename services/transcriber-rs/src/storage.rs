@@ -1,21 +1,30 @@
-use anyhow::{Context, Result};
+use futures::StreamExt;
 use s3::creds::Credentials;
-use s3::{Bucket, Region};
+use s3::error::S3Error;
+use s3::{Bucket, BucketConfiguration, Region};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
 use std::path::Path;
+use std::time::Duration;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::config::Config;
+use crate::error::{Result, TranscribeError};
 
 pub struct Storage {
     bucket: Box<Bucket>,
+    max_retries: u32,
+    retry_base_delay: Duration,
+    max_download_bytes: u64,
 }
 
 impl Storage {
     pub fn new(config: &Config) -> Result<Self> {
         let region = Region::Custom {
-            region: "us-east-1".to_string(),
+            region: config.s3_region.clone(),
             endpoint: format!(
                 "{}://{}",
                 if config.minio_use_ssl { "https" } else { "http" },
@@ -29,39 +38,228 @@ impl Storage {
             None,
             None,
             None,
-        )?;
+        )
+        .map_err(|e| TranscribeError::Storage(format!("Invalid credentials: {}", e)))?;
 
-        let bucket = Bucket::new(&config.minio_bucket, region, credentials)?
-            .with_path_style();
+        // MinIO needs path-style URLs; AWS S3 expects virtual-hosted ones
+        let bucket = Bucket::new(&config.minio_bucket, region, credentials)?;
+        let bucket = if config.s3_path_style {
+            bucket.with_path_style()
+        } else {
+            bucket
+        };
 
-        Ok(Self { bucket })
+        Ok(Self {
+            bucket,
+            max_retries: config.download_max_retries,
+            retry_base_delay: Duration::from_millis(config.download_retry_base_ms),
+            max_download_bytes: config.max_download_bytes,
+        })
     }
 
-    pub async fn download_file(&self, object_key: &str, local_path: &Path) -> Result<()> {
-        info!("Downloading {} to {:?}", object_key, local_path);
+    /// Check that the bucket exists, creating it when `create` is set, so a
+    /// missing bucket fails startup instead of the first download
+    pub async fn ensure_bucket(&self, create: bool) -> Result<()> {
+        let name = self.bucket.name();
 
-        // Get object from S3/MinIO
-        let response = self
+        // Listing one key is the cheapest request that fails for a missing bucket
+        match self
             .bucket
-            .get_object(object_key)
+            .list_page(String::new(), None, None, None, Some(1))
             .await
-            .context("Failed to get object from MinIO")?;
+        {
+            Ok(_) => return Ok(()),
+            Err(S3Error::HttpFailWithBody(404, _)) => {}
+            Err(S3Error::HttpFailWithBody(403, _)) => {
+                warn!("Not allowed to list bucket {}; assuming it exists", name);
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(TranscribeError::Storage(format!(
+                    "Failed to reach bucket {}: {}",
+                    name, e
+                )))
+            }
+        }
 
-        // Write to local file
-        let mut file = File::create(local_path)
-            .await
-            .context("Failed to create local file")?;
+        if !create {
+            return Err(TranscribeError::Storage(format!(
+                "Bucket {} does not exist; create it or set MINIO_CREATE_BUCKET=true",
+                name
+            )));
+        }
 
-        file.write_all(response.bytes())
-            .await
-            .context("Failed to write file")?;
+        let region = self.bucket.region();
+        let credentials = self.bucket.credentials().await?;
+        let response = if self.bucket.is_path_style() {
+            Bucket::create_with_path_style(
+                &name,
+                region,
+                credentials,
+                BucketConfiguration::default(),
+            )
+            .await?
+        } else {
+            Bucket::create(&name, region, credentials, BucketConfiguration::default()).await?
+        };
+        if !response.success() {
+            return Err(TranscribeError::Storage(format!(
+                "Failed to create bucket {}: HTTP {} {}",
+                name, response.response_code, response.response_text
+            )));
+        }
+
+        info!("Created bucket {}", name);
+        Ok(())
+    }
+
+    /// Download `object_key` to `local_path`: the given version of it, or
+    /// the latest when `version_id` is `None`
+    pub async fn download_file(
+        &self,
+        object_key: &str,
+        version_id: Option<&str>,
+        local_path: &Path,
+    ) -> Result<()> {
+        info!("Downloading {} to {:?}", object_key, local_path);
+
+        // Check the size up front so oversized objects are never fetched
+        let len = self.object_size(object_key, version_id).await?;
+        if len > self.max_download_bytes {
+            return Err(TranscribeError::TooLarge(format!(
+                "Audio file is {} bytes, over the {} byte limit",
+                len, self.max_download_bytes
+            )));
+        }
+
+        // Get object from S3/MinIO
+        let bucket = self.bucket_for(version_id)?;
+        let mut response = self
+            .with_retries(object_key, || bucket.get_object_stream(object_key))
+            .await?;
+
+        // Write chunks to the local file as they arrive so memory stays bounded
+        let mut file = File::create(local_path).await?;
+        let mut written: u64 = 0;
+
+        while let Some(chunk) = response.bytes().next().await {
+            let chunk = chunk?;
+            written += chunk.len() as u64;
+            // The object may have grown since the HEAD request
+            if written > self.max_download_bytes {
+                return Err(TranscribeError::TooLarge(format!(
+                    "Audio file exceeds the {} byte limit",
+                    self.max_download_bytes
+                )));
+            }
+            file.write_all(&chunk).await?;
+        }
 
         file.flush().await?;
 
-        info!("Downloaded {} bytes", response.bytes().len());
+        info!("Downloaded {} bytes", written);
+        Ok(())
+    }
+
+    pub async fn delete_object(&self, object_key: &str) -> Result<()> {
+        self.with_retries(object_key, || self.bucket.delete_object(object_key))
+            .await?;
+
+        info!("Deleted {}", object_key);
+        Ok(())
+    }
+
+    /// Read a whole object into memory; for small objects of our own only
+    pub async fn download_bytes(&self, object_key: &str) -> Result<Vec<u8>> {
+        let response = self
+            .with_retries(object_key, || self.bucket.get_object(object_key))
+            .await?;
+        Ok(response.to_vec())
+    }
+
+    /// Size in bytes of `object_key` (or of its `version_id`), which must exist
+    pub async fn object_size(&self, object_key: &str, version_id: Option<&str>) -> Result<u64> {
+        let bucket = self.bucket_for(version_id)?;
+        let (head, _) = self
+            .with_retries(object_key, || bucket.head_object(object_key))
+            .await
+            .map_err(|e| match (e, version_id) {
+                // The object may well exist; it's the version that doesn't
+                (TranscribeError::AudioNotFound, Some(version_id)) => TranscribeError::NotFound(
+                    format!("Version {} of {} does not exist", version_id, object_key),
+                ),
+                (e, _) => e,
+            })?;
+        Ok(head.content_length.unwrap_or_default() as u64)
+    }
+
+    /// Upload an in-memory object to `object_key`
+    pub async fn upload_bytes(
+        &self,
+        object_key: &str,
+        content: &[u8],
+        content_type: &str,
+    ) -> Result<()> {
+        self.with_retries(object_key, || {
+            self.bucket
+                .put_object_with_content_type(object_key, content, content_type)
+        })
+        .await?;
+
+        info!("Uploaded {} bytes to {}", content.len(), object_key);
         Ok(())
     }
 
+    /// A URL that grants read access to `object_key` (or its `version_id`)
+    /// for `expiry_secs` without our credentials
+    pub async fn presign_get(
+        &self,
+        object_key: &str,
+        version_id: Option<&str>,
+        expiry_secs: u32,
+    ) -> Result<String> {
+        Ok(self
+            .bucket_for(version_id)?
+            .presign_get(object_key, expiry_secs, None)
+            .await?)
+    }
+
+    /// The bucket, or with `version_id` a copy whose requests address that
+    /// version of an object
+    fn bucket_for(&self, version_id: Option<&str>) -> Result<Cow<'_, Bucket>> {
+        match version_id {
+            Some(version_id) => {
+                let query = HashMap::from([("versionId".to_string(), version_id.to_string())]);
+                Ok(Cow::Owned(self.bucket.with_extra_query(query)?))
+            }
+            None => Ok(Cow::Borrowed(&self.bucket)),
+        }
+    }
+
+    /// Run an S3 request, retrying transient failures with exponential backoff
+    async fn with_retries<T, F, Fut>(&self, object_key: &str, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::result::Result<T, S3Error>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match request().await {
+                Ok(response) => return Ok(response),
+                Err(e) if attempt < self.max_retries && is_retryable(&e) => {
+                    attempt += 1;
+                    let delay = self.retry_base_delay * 2u32.saturating_pow(attempt - 1);
+                    warn!(
+                        "Request for {} failed (attempt {}/{}): {}; retrying in {:?}",
+                        object_key, attempt, self.max_retries, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+
     pub fn normalize_object_key<'a>(&self, file_url: &'a str, bucket_name: &str) -> &'a str {
         // Handle various URL formats:
         // - "recordings/meeting-123/user-456.ogg"
@@ -72,3 +270,12 @@ impl Storage {
             .unwrap_or(file_url)
     }
 }
+
+/// Connection-level failures and 5xx responses are worth retrying; 4xx are not
+fn is_retryable(error: &S3Error) -> bool {
+    match error {
+        S3Error::HttpFailWithBody(status, _) => *status >= 500,
+        S3Error::Hyper(_) | S3Error::Io(_) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt::Write;
+
+use crate::handlers::SegmentResponse;
+
+/// Render segments as SubRip (SRT) subtitles
+pub fn to_srt(segments: &[SegmentResponse]) -> String {
+    let mut out = String::new();
+
+    for (i, (start, end, text)) in cues(segments).into_iter().enumerate() {
+        let _ = writeln!(out, "{}", i + 1);
+        let _ = writeln!(
+            out,
+            "{} --> {}",
+            format_timestamp(start, ','),
+            format_timestamp(end, ',')
+        );
+        let _ = writeln!(out, "{}", text);
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Render segments as WebVTT subtitles
+pub fn to_vtt(segments: &[SegmentResponse]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+
+    for (start, end, text) in cues(segments) {
+        let _ = writeln!(
+            out,
+            "{} --> {}",
+            format_timestamp(start, '.'),
+            format_timestamp(end, '.')
+        );
+        let _ = writeln!(out, "{}", text);
+        out.push('\n');
+    }
+
+    out
+}
+
+/// Build subtitle cues, skipping empty text and clamping each cue's end
+/// to the start of the next one so cues never overlap.
+fn cues(segments: &[SegmentResponse]) -> Vec<(f64, f64, &str)> {
+    let non_empty: Vec<&SegmentResponse> = segments
+        .iter()
+        .filter(|s| !s.text.trim().is_empty())
+        .collect();
+
+    non_empty
+        .iter()
+        .enumerate()
+        .map(|(i, segment)| {
+            let mut end = segment.end;
+            if let Some(next) = non_empty.get(i + 1) {
+                end = end.min(next.start);
+            }
+            (segment.start, end.max(segment.start), segment.text.trim())
+        })
+        .collect()
+}
+
+/// Format seconds as `HH:MM:SS<sep>mmm`
+fn format_timestamp(seconds: f64, millis_separator: char) -> String {
+    let total_ms = (seconds.max(0.0) * 1000.0).round() as u64;
+    let hours = total_ms / 3_600_000;
+    let minutes = (total_ms % 3_600_000) / 60_000;
+    let secs = (total_ms % 60_000) / 1000;
+    let millis = total_ms % 1000;
+
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        hours, minutes, secs, millis_separator, millis
+    )
+}
@@ -1,18 +1,38 @@
-use anyhow::{Context, Result};
-use parakeet_rs::{ParakeetTDT, TimestampMode, Transcriber as ParakeetTranscriber};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::future::Future;
+use std::io::{Cursor, Read};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::sync::Arc;
-use tempfile::NamedTempFile;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
-use tracing::{info, warn};
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::chunking::{self, ProfileChunking};
+use crate::diarization::{self, Diarizer};
+use crate::engine::{Backend, EngineSpec, ModelPrecision, TimedWord, TranscriptionEngine};
+use crate::error::{Result, TranscribeError};
+use crate::silence::LevelEnvelope;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Segment {
     pub start: f64,
     pub end: f64,
     pub text: String,
+    /// Model confidence for this segment, when the engine provides one
+    pub confidence: Option<f32>,
+    /// Speaker label (e.g. `speaker_0`) when diarization was requested
+    pub speaker: Option<String>,
+    /// RMS level (dBFS) of the audio under this segment; words over
+    /// near-silence are usually hallucinated
+    #[serde(default)]
+    pub level_db: Option<f32>,
+    /// Language of this segment when the engine reports one per word, for
+    /// audio that switches languages; otherwise see the result's `language`
+    #[serde(default)]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,155 +40,1182 @@ pub struct TranscriptionResult {
     pub text: String,
     pub segments: Vec<Segment>,
     pub duration: f64,
+    /// Language the transcript is reported in (hint or configured default)
+    pub language: Option<String>,
+    /// Transcription stopped early; the segments cover only the audio before
+    /// the failure described by `warning`
+    #[serde(default)]
+    pub partial: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning: Option<String>,
+    /// Properties of the source audio, before conversion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_info: Option<AudioInfo>,
+    /// Time spent producing this result; not stored with cached results
+    #[serde(skip)]
+    pub timings: StageTimings,
+}
+
+/// Properties of a source audio file, read from its headers. Each is `None`
+/// when the headers don't say.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AudioInfo {
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    /// ffmpeg codec name, e.g. `opus` or `pcm_s16le`
+    pub codec: Option<String>,
+    /// Length in seconds of the whole file, before any clipping
+    pub original_duration: Option<f64>,
+}
+
+/// Wall-clock milliseconds spent in each stage of a transcription
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageTimings {
+    pub download_ms: u64,
+    pub convert_ms: u64,
+    pub inference_ms: u64,
+}
+
+/// Per-request transcription options
+#[derive(Debug, Clone, Default)]
+pub struct TranscribeOptions {
+    /// ISO 639-1 language hint. Parakeet TDT v3 identifies the spoken language
+    /// on its own, so the hint does not steer decoding; it is reported back on
+    /// the result so callers know which language they asked for.
+    pub language: Option<String>,
+    /// Label segments with speakers (requires a loaded diarization model)
+    pub diarize: bool,
+    /// Only transcribe this part of the file
+    pub clip: Option<Clip>,
+    /// Speed/quality tradeoff; the configured defaults when unset
+    pub profile: Option<Profile>,
+}
+
+/// A speed/quality tradeoff clients pick instead of engine internals. Parakeet
+/// decodes greedily (there is no beam to widen) and runs at the precision of
+/// the loaded model, so profiles differ in how long audio is windowed:
+/// shorter windows with less overlap are cheaper, longer ones with more
+/// overlap lose fewer words at window seams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// Throughput first, for rough drafts
+    Fast,
+    /// Quality first, for final transcripts
+    Accurate,
+}
+
+impl Profile {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Fast => "fast",
+            Self::Accurate => "accurate",
+        }
+    }
+}
+
+/// A time range (seconds) within a file; open-ended without `end`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Clip {
+    pub start: f64,
+    pub end: Option<f64>,
+}
+
+/// Slack allowed between a clip's requested and decoded length
+const CLIP_TOLERANCE_SECS: f64 = 0.1;
+
+/// Decoded audio: interleaved samples in `[-1, 1]`
+struct Audio {
+    samples: Vec<f32>,
+    sample_rate: u32,
+    channels: u16,
+}
+
+impl Audio {
+    fn duration(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate as f64 / self.channels as f64
+    }
+}
+
+/// Decoded audio shorter than this is too short to judge drift by
+const MIN_DRIFT_CHECK_SECS: f64 = 1.0;
+
+/// Format audio is converted to before transcription
+#[derive(Debug, Clone)]
+pub struct AudioFormat {
+    pub sample_rate: u32,
+    pub channels: u16,
+    /// Extra ffmpeg filter chain applied during conversion
+    pub filter: Option<String>,
+    /// Apply EBU R128 loudness normalization (`loudnorm`)
+    pub normalize: bool,
+    /// Apply FFT noise reduction (`afftdn`)
+    pub denoise: bool,
+    /// Largest difference (seconds) between the decoded length and the
+    /// source's length that isn't treated as timestamp drift
+    pub drift_tolerance_secs: f64,
+    /// Rescale timestamps onto the source's timeline when they drift
+    pub rescale_drift: bool,
+}
+
+impl AudioFormat {
+    /// The full `-af` chain: denoise, then normalize, then the custom filter
+    fn filter_chain(&self) -> Option<String> {
+        let filters: Vec<&str> = [
+            self.denoise.then_some("afftdn"),
+            self.normalize.then_some("loudnorm=print_format=summary"),
+            self.filter.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+
+    /// Factor mapping decoded time onto the source's timeline, when
+    /// `decoded` and `expected` seconds differ beyond the tolerance and
+    /// rescaling is enabled. Drift is logged either way.
+    fn drift_scale(&self, decoded: f64, expected: f64) -> Option<f64> {
+        let drift = decoded - expected;
+        if decoded < MIN_DRIFT_CHECK_SECS || drift.abs() <= self.drift_tolerance_secs {
+            return None;
+        }
+
+        warn!(
+            "Decoded audio is {:.3}s but the source is {:.3}s ({:+.3}s drift){}",
+            decoded,
+            expected,
+            drift,
+            if self.rescale_drift {
+                "; rescaling timestamps"
+            } else {
+                ""
+            }
+        );
+        self.rescale_drift.then(|| expected / decoded)
+    }
+}
+
+impl Default for AudioFormat {
+    /// 16kHz mono, which is what Parakeet expects
+    fn default() -> Self {
+        Self {
+            sample_rate: 16000,
+            channels: 1,
+            filter: None,
+            normalize: false,
+            denoise: false,
+            drift_tolerance_secs: 0.25,
+            rescale_drift: false,
+        }
+    }
 }
 
+/// Loaded instances of the model. The first serves single-pass work; windowed
+/// transcription spreads its windows over all of them.
+type Engine = Arc<Mutex<Option<Vec<Box<dyn TranscriptionEngine>>>>>;
+
 pub struct Transcriber {
-    engine: Arc<Mutex<Option<ParakeetTDT>>>,
-    model_loaded: bool,
+    /// Current engine; swapped wholesale on reload so in-flight work keeps its own Arc
+    engine: RwLock<Engine>,
+    model_loaded: AtomicBool,
+    model_path: RwLock<Option<PathBuf>>,
+    /// Precision of the loaded model; `Auto` when its files weren't recognized
+    loaded_precision: RwLock<Option<ModelPrecision>>,
+    /// Serializes reloads so two concurrent loads don't race on the swap
+    reload_lock: Mutex<()>,
+    diarizer: RwLock<Option<Arc<Diarizer>>>,
+    audio_format: AudioFormat,
+    timeout: Option<Duration>,
+    chunking: ProfileChunking,
+    /// Audio longer than this is rejected before it reaches the model
+    max_audio_secs: Option<f64>,
+    /// Model instances to load, so windows of one file run concurrently
+    window_workers: usize,
+    engine_spec: EngineSpec,
 }
 
 impl Transcriber {
-    pub fn new() -> Self {
+    pub fn new(
+        audio_format: AudioFormat,
+        timeout: Option<Duration>,
+        chunking: ProfileChunking,
+        max_audio_secs: Option<f64>,
+        window_workers: usize,
+        engine_spec: EngineSpec,
+    ) -> Self {
         Self {
-            engine: Arc::new(Mutex::new(None)),
-            model_loaded: false,
+            engine: RwLock::new(Arc::new(Mutex::new(None))),
+            model_loaded: AtomicBool::new(false),
+            model_path: RwLock::new(None),
+            loaded_precision: RwLock::new(None),
+            reload_lock: Mutex::new(()),
+            diarizer: RwLock::new(None),
+            audio_format,
+            timeout,
+            chunking,
+            max_audio_secs,
+            window_workers: window_workers.max(1),
+            engine_spec,
+        }
+    }
+
+    /// Reject audio longer than the configured limit
+    pub fn check_length(&self, audio_duration: f64) -> Result<()> {
+        if let Some(max_audio_secs) = self.max_audio_secs {
+            if audio_duration > max_audio_secs {
+                return Err(TranscribeError::TooLarge(format!(
+                    "audio is {:.0} seconds long, the limit is {:.0} seconds",
+                    audio_duration, max_audio_secs
+                )));
+            }
         }
+        Ok(())
     }
 
-    pub async fn load_model(&mut self, model_path: &Path) -> Result<()> {
-        info!("Loading Parakeet TDT model from {:?}...", model_path);
+    /// Remember the model path without loading it; the first transcription
+    /// loads it instead
+    pub fn load_model_lazily(&self, model_path: &Path) {
+        info!("Deferring model load from {:?} until first use", model_path);
+        *self.model_path.write().unwrap() = Some(model_path.to_path_buf());
+    }
 
-        let model_path = model_path.to_path_buf();
-        let engine = self.engine.clone();
+    /// Load a deferred model if it isn't loaded yet. Concurrent first requests
+    /// wait on the reload lock, and only the first of them actually loads.
+    async fn ensure_loaded(&self) -> Result<()> {
+        if self.is_loaded() {
+            return Ok(());
+        }
+        let model_path = self.model_path().ok_or(TranscribeError::ModelNotReady)?;
 
-        // Load model in blocking task (model loading is CPU-intensive)
-        tokio::task::spawn_blocking(move || {
-            let parakeet = ParakeetTDT::from_pretrained(&model_path, None)
-                .context("Failed to load Parakeet TDT model")?;
+        let _reload = self.reload_lock.lock().await;
+        if self.is_loaded() {
+            return Ok(());
+        }
 
-            let mut guard = futures::executor::block_on(engine.lock());
-            *guard = Some(parakeet);
-            Ok::<_, anyhow::Error>(())
-        })
-        .await??;
+        info!(
+            "Loading {} model from {:?}...",
+            self.engine_spec.backend.as_str(),
+            model_path
+        );
+        let (engines, precision) =
+            load_engine(self.engine_spec.clone(), &model_path, self.window_workers).await?;
+        self.swap_engine(engines, &model_path, precision);
 
-        self.model_loaded = true;
-        info!("Parakeet TDT model loaded successfully!");
+        info!("Model loaded successfully!");
         Ok(())
     }
 
-    pub fn is_ready(&self) -> bool {
-        self.model_loaded
+    pub async fn load_model(&self, model_path: &Path) -> Result<()> {
+        let _reload = self.reload_lock.lock().await;
+        info!(
+            "Loading {} model from {:?}...",
+            self.engine_spec.backend.as_str(),
+            model_path
+        );
+
+        let (engines, precision) =
+            load_engine(self.engine_spec.clone(), model_path, self.window_workers).await?;
+        self.swap_engine(engines, model_path, precision);
+
+        info!("Model loaded successfully!");
+        Ok(())
     }
 
-    /// Convert audio to WAV format if needed (using ffmpeg)
-    async fn ensure_wav_format(&self, audio_path: &Path) -> Result<Option<NamedTempFile>> {
-        let extension = audio_path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
+    /// Load a model into a fresh slot and swap it in only after loading succeeds.
+    /// Transcriptions already running finish on the previous model, which is
+    /// dropped once the last of them releases it.
+    pub async fn reload_model(&self, model_path: &Path) -> Result<()> {
+        let _reload = self.reload_lock.lock().await;
+        info!(
+            "Reloading {} model from {:?}...",
+            self.engine_spec.backend.as_str(),
+            model_path
+        );
 
-        // If already WAV, no conversion needed
-        if extension == "wav" {
-            return Ok(None);
+        let (engines, precision) =
+            load_engine(self.engine_spec.clone(), model_path, self.window_workers).await?;
+        self.swap_engine(engines, model_path, precision);
+
+        info!("Model reloaded successfully!");
+        Ok(())
+    }
+
+    /// Run one transcription of a second of silence so the engine's lazy
+    /// allocations happen before the first real request
+    pub async fn warm_up(&self, temp_dir: &Path) -> Result<Duration> {
+        let started = Instant::now();
+
+        let silence = tempfile::Builder::new()
+            .suffix(".wav")
+            .tempfile_in(temp_dir)?;
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(silence.path(), spec)
+            .map_err(|e| TranscribeError::Internal(e.to_string()))?;
+        for _ in 0..spec.sample_rate {
+            writer
+                .write_sample(0i16)
+                .map_err(|e| TranscribeError::Internal(e.to_string()))?;
+        }
+        writer
+            .finalize()
+            .map_err(|e| TranscribeError::Internal(e.to_string()))?;
+
+        self.transcribe(silence.path(), &TranscribeOptions::default())
+            .await?;
+        Ok(started.elapsed())
+    }
+
+    /// Transcribe a second of silence held in memory, returning how long it
+    /// took. Proves the loaded engine can still run inference; holds the
+    /// engine for the duration like any transcription.
+    pub async fn check_inference(&self) -> Result<Duration> {
+        if !self.is_loaded() {
+            return Err(TranscribeError::ModelNotReady);
+        }
+
+        let started = Instant::now();
+        let sample_rate = self.audio_format.sample_rate;
+        self.transcribe_samples(vec![0.0; sample_rate as usize], sample_rate)
+            .await?;
+        Ok(started.elapsed())
+    }
+
+    fn swap_engine(
+        &self,
+        engines: Vec<Box<dyn TranscriptionEngine>>,
+        model_path: &Path,
+        precision: ModelPrecision,
+    ) {
+        *self.engine.write().unwrap() = Arc::new(Mutex::new(Some(engines)));
+        *self.model_path.write().unwrap() = Some(model_path.to_path_buf());
+        *self.loaded_precision.write().unwrap() = Some(precision);
+        self.model_loaded.store(true, Ordering::SeqCst);
+    }
+
+    /// Resolves once the current engine is free: after inference already
+    /// running on it, which nobody may be waiting for any more, finishes
+    pub fn engine_idle(&self) -> impl Future<Output = ()> + Send + 'static {
+        let engine = self.engine.read().unwrap().clone();
+        async move {
+            drop(engine.lock().await);
         }
+    }
+
+    /// Whether requests can be served: the model is loaded, or a lazily
+    /// loaded model is waiting for its first request
+    pub fn is_ready(&self) -> bool {
+        self.is_loaded() || self.model_path.read().unwrap().is_some()
+    }
 
-        info!("Converting {} to WAV format", audio_path.display());
+    pub fn is_loaded(&self) -> bool {
+        self.model_loaded.load(Ordering::SeqCst)
+    }
+
+    /// Load the speaker diarization model, enabling `TranscribeOptions::diarize`
+    pub async fn load_diarizer(&self, model_path: &Path) -> Result<()> {
+        info!("Loading diarization model from {:?}...", model_path);
 
-        // Create temp file for WAV output
-        let temp_wav = NamedTempFile::new().context("Failed to create temp WAV file")?;
-        let wav_path = temp_wav.path().to_path_buf();
+        let diarizer = Diarizer::load(model_path).await?;
+        *self.diarizer.write().unwrap() = Some(Arc::new(diarizer));
+
+        info!("Diarization model loaded successfully!");
+        Ok(())
+    }
+
+    /// Engine implementation models are loaded into
+    pub fn backend(&self) -> Backend {
+        self.engine_spec.backend
+    }
+
+    /// Precision of the loaded model, if one is loaded
+    pub fn loaded_precision(&self) -> Option<ModelPrecision> {
+        *self.loaded_precision.read().unwrap()
+    }
+
+    /// Path of the current model (loaded, or pending when loading lazily), if any
+    pub fn model_path(&self) -> Option<PathBuf> {
+        self.model_path.read().unwrap().clone()
+    }
+
+    /// Everything besides the audio and request options that shapes a
+    /// transcription: the model, how audio is decoded, and how a `profile`
+    /// is windowed. Cached results are keyed by it, so a reload or a config
+    /// change never serves results made under the old setup.
+    pub fn cache_identity(&self, profile: Option<Profile>) -> String {
+        let format = &self.audio_format;
+        let chunking = self.chunking.for_profile(profile);
+        format!(
+            "backend={}|model={}|precision={}|format={}x{}|filters={}|drift={}:{}|window={}",
+            self.engine_spec.backend.as_str(),
+            self.model_path().unwrap_or_default().display(),
+            self.loaded_precision()
+                .unwrap_or(self.engine_spec.precision)
+                .as_str(),
+            format.sample_rate,
+            format.channels,
+            format.filter_chain().unwrap_or_default(),
+            format.rescale_drift,
+            format.drift_tolerance_secs,
+            chunking
+                .map(|c| format!("{}/{}", c.window_secs, c.overlap_secs))
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Decode audio into samples in the configured format. Matching WAV is
+    /// read directly; anything else is converted by ffmpeg, whose raw PCM
+    /// output is read from its stdout rather than written to a temp file. The
+    /// decision is made from the file content, since downloads often have no
+    /// extension or a misleading one. Only the first audio track is decoded,
+    /// so video files (MP4, MOV, ...) are handled like audio. With a clip,
+    /// ffmpeg decodes only that range.
+    async fn decode_audio(&self, audio_path: &Path, clip: Option<Clip>) -> Result<Audio> {
+        // Only skip conversion for whole WAV files that already match the target format
+        let filter_chain = self.audio_format.filter_chain();
+        if filter_chain.is_none() && clip.is_none() && is_wav(audio_path)? {
+            let matches = hound::WavReader::open(audio_path).is_ok_and(|reader| {
+                let spec = reader.spec();
+                spec.sample_rate == self.audio_format.sample_rate
+                    && spec.channels == self.audio_format.channels
+            });
+            if matches {
+                let path = audio_path.to_path_buf();
+                let (samples, sample_rate, channels) =
+                    tokio::task::spawn_blocking(move || read_wav_samples(&path)).await??;
+                return Ok(Audio {
+                    samples,
+                    sample_rate,
+                    channels,
+                });
+            }
+        }
+
+        info!("Converting {} with ffmpeg", audio_path.display());
 
         let audio_path_clone = audio_path.to_path_buf();
+        let format = self.audio_format.clone();
 
         // Run ffmpeg conversion in blocking task
         tokio::task::spawn_blocking(move || {
-            let output = Command::new("ffmpeg")
+            let mut command = Command::new("ffmpeg");
+            // Seeking before the input skips straight to the clip
+            if let Some(clip) = clip {
+                command.args(["-ss", &clip.start.to_string()]);
+            }
+            command.args([
+                "-i", audio_path_clone.to_str().unwrap(),
+                "-map", "0:a:0",                         // first audio track only (skips video)
+                "-ar", &format.sample_rate.to_string(),  // 16kHz by default (optimal for speech)
+                "-ac", &format.channels.to_string(),     // mono by default
+            ]);
+            if let Some(end) = clip.and_then(|clip| clip.end) {
+                let length = end - clip.map(|clip| clip.start).unwrap_or_default();
+                command.args(["-t", &length.to_string()]);
+            }
+            if let Some(ref filter) = filter_chain {
+                command.args(["-af", filter]);
+            }
+            let output = command
                 .args([
-                    "-i", audio_path_clone.to_str().unwrap(),
-                    "-ar", "16000",     // 16kHz sample rate (optimal for speech)
-                    "-ac", "1",         // mono
-                    "-f", "wav",        // WAV format
-                    "-y",               // overwrite
-                    wav_path.to_str().unwrap(),
+                    "-f", "s16le",      // raw 16-bit PCM, no container
+                    "pipe:1",           // to stdout
                 ])
                 .output()
-                .context("Failed to run ffmpeg")?;
+                .map_err(|e| spawn_error("ffmpeg", e))?;
 
+            // A non-zero exit means ffmpeg couldn't read the input; the end of
+            // its stderr says why
             if !output.status.success() {
                 let stderr = String::from_utf8_lossy(&output.stderr);
                 warn!("ffmpeg stderr: {}", stderr);
-                anyhow::bail!("ffmpeg conversion failed: {}", stderr);
+                if stderr.contains("matches no streams") {
+                    return Err(TranscribeError::ConversionFailed(
+                        "file has no audio track".to_string(),
+                    ));
+                }
+                return Err(TranscribeError::ConversionFailed(format!(
+                    "ffmpeg exited with {}: {}",
+                    output.status,
+                    stderr_tail(&stderr)
+                )));
             }
 
-            info!("Audio converted to WAV successfully");
-            Ok::<_, anyhow::Error>(())
+            if format.normalize {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                if let (Some(input), Some(output)) = (
+                    loudnorm_value(&stderr, "Input Integrated:"),
+                    loudnorm_value(&stderr, "Output Integrated:"),
+                ) {
+                    info!("Loudness normalized from {} to {}", input, output);
+                }
+            }
+
+            let samples = output
+                .stdout
+                .chunks_exact(2)
+                .map(|b| i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0)
+                .collect();
+
+            info!("Audio converted successfully");
+            Ok(Audio {
+                samples,
+                sample_rate: format.sample_rate,
+                channels: format.channels,
+            })
+        })
+        .await?
+    }
+
+    pub async fn transcribe(
+        &self,
+        audio_path: &Path,
+        options: &TranscribeOptions,
+    ) -> Result<TranscriptionResult> {
+        self.transcribe_with_callback(audio_path, options, |_| {})
+            .await
+    }
+
+    /// Transcribe audio, invoking `on_segment` for each segment as it is produced
+    pub async fn transcribe_with_callback<F>(
+        &self,
+        audio_path: &Path,
+        options: &TranscribeOptions,
+        on_segment: F,
+    ) -> Result<TranscriptionResult>
+    where
+        F: FnMut(&Segment),
+    {
+        self.ensure_loaded().await?;
+        let diarizer = self.diarizer_for(options)?;
+
+        // Fail clearly on empty or truncated downloads instead of deep inside ffmpeg
+        validate_audio(audio_path).await?;
+
+        // Refuse long files by their headers before spending a conversion on
+        // them; the decoded length is checked again below
+        let audio_info = probe_audio_info(audio_path).await;
+        if let Some(duration) = audio_info.as_ref().and_then(|info| info.original_duration) {
+            self.check_length(clipped_length(options.clip, duration))?;
+        }
+
+        // Decode to samples in the model's format
+        let convert_started = Instant::now();
+        let audio = self
+            .decode_audio(audio_path, options.clip)
+            .instrument(info_span!("convert"))
+            .await?;
+        let convert_ms = convert_started.elapsed().as_millis() as u64;
+
+        self.transcribe_audio(audio, audio_info, convert_ms, diarizer, options, on_segment)
+            .await
+    }
+
+    /// Whether `wav` can be transcribed straight from memory: a WAV file
+    /// already in the target format, with no filters or clip to apply
+    pub fn decodes_in_memory(&self, wav: &[u8], options: &TranscribeOptions) -> bool {
+        self.audio_format.filter_chain().is_none()
+            && options.clip.is_none()
+            && hound::WavReader::new(Cursor::new(wav)).is_ok_and(|reader| {
+                let spec = reader.spec();
+                spec.sample_rate == self.audio_format.sample_rate
+                    && spec.channels == self.audio_format.channels
+            })
+    }
+
+    /// Transcribe a WAV file held in memory, without a temp file or ffmpeg.
+    /// Only for audio `decodes_in_memory` accepts.
+    pub async fn transcribe_wav_bytes(
+        &self,
+        wav: Vec<u8>,
+        options: &TranscribeOptions,
+    ) -> Result<TranscriptionResult> {
+        self.ensure_loaded().await?;
+        let diarizer = self.diarizer_for(options)?;
+
+        let convert_started = Instant::now();
+        let (audio, codec) = tokio::task::spawn_blocking(move || {
+            let reader = hound::WavReader::new(Cursor::new(wav))
+                .map_err(|e| TranscribeError::Unsupported(format!("Invalid WAV file: {}", e)))?;
+            let codec = wav_codec(reader.spec());
+            let (samples, sample_rate, channels) = wav_samples(reader)?;
+            Ok::<_, TranscribeError>((
+                Audio {
+                    samples,
+                    sample_rate,
+                    channels,
+                },
+                codec,
+            ))
         })
         .await??;
+        let convert_ms = convert_started.elapsed().as_millis() as u64;
+        if audio.samples.is_empty() {
+            return Err(TranscribeError::InvalidRequest(
+                "audio file is empty or corrupt".to_string(),
+            ));
+        }
 
-        Ok(Some(temp_wav))
+        let audio_info = AudioInfo {
+            sample_rate: Some(audio.sample_rate),
+            channels: Some(audio.channels),
+            codec: Some(codec),
+            original_duration: Some(audio.duration()),
+        };
+        self.transcribe_audio(
+            audio,
+            Some(audio_info),
+            convert_ms,
+            diarizer,
+            options,
+            |_| {},
+        )
+        .await
     }
 
-    pub async fn transcribe(&self, audio_path: &Path) -> Result<TranscriptionResult> {
-        if !self.model_loaded {
-            anyhow::bail!("Model not loaded");
+    /// The diarizer, when the options ask for speakers
+    fn diarizer_for(&self, options: &TranscribeOptions) -> Result<Option<Arc<Diarizer>>> {
+        if !options.diarize {
+            return Ok(None);
+        }
+        let diarizer = self.diarizer.read().unwrap().clone();
+        diarizer.map(Some).ok_or_else(|| {
+            TranscribeError::InvalidRequest("Diarization is not enabled".to_string())
+        })
+    }
+
+    /// Run decoded audio through the engine (and the diarizer), then turn the
+    /// words into timed segments on the source's timeline
+    async fn transcribe_audio<F>(
+        &self,
+        audio: Audio,
+        audio_info: Option<AudioInfo>,
+        convert_ms: u64,
+        diarizer: Option<Arc<Diarizer>>,
+        options: &TranscribeOptions,
+        mut on_segment: F,
+    ) -> Result<TranscriptionResult>
+    where
+        F: FnMut(&Segment),
+    {
+        let audio_duration = audio.duration();
+
+        if let Some(clip) = options.clip {
+            check_clip(clip, audio_duration)?;
         }
 
-        // Convert to WAV if needed (parakeet-rs requires WAV format)
-        let wav_path = self.ensure_wav_format(audio_path).await?;
-        let audio_path_for_transcription = wav_path.as_ref().map(|p| p.path().to_path_buf())
-            .unwrap_or_else(|| audio_path.to_path_buf());
-        let audio_path_for_duration = audio_path_for_transcription.clone();
-        let engine = self.engine.clone();
+        // Long files would hold the model mutex for minutes; refuse them
+        // before they get there
+        self.check_length(audio_duration)?;
+        let engine = self.engine.read().unwrap().clone();
+        let chunking = self.chunking.for_profile(options.profile);
+        let span = info_span!(
+            "inference",
+            model_path = %self.model_path().unwrap_or_default().display(),
+            audio_duration,
+        );
+
+        // Decoders may add priming samples or lose a few frames (Opus does
+        // both), shifting every later word; compare against the source's
+        // own length
+        let expected_duration = audio_info
+            .as_ref()
+            .and_then(|info| info.original_duration)
+            .map(|duration| clipped_length(options.clip, duration));
+        let scale = expected_duration
+            .and_then(|expected| self.audio_format.drift_scale(audio_duration, expected))
+            .unwrap_or(1.0);
+
+        // Timestamps of a clip are relative to its start; report them in the
+        // original file's time
+        let offset = options.clip.map(|clip| clip.start).unwrap_or_default();
+
+        // Segments come back a window at a time, so callers can show them
+        // before the whole file is done. Speakers are only known once the
+        // pass finishes, so diarized segments are handed over at the end.
+        let streaming = diarizer.is_none();
+        let (segment_tx, mut segment_rx) = tokio::sync::mpsc::unbounded_channel();
 
         // Run transcription in blocking task (inference is CPU-intensive)
-        let result = tokio::task::spawn_blocking(move || {
+        let inference = tokio::task::spawn_blocking(move || {
+            let _span = span.entered();
+            let Audio {
+                samples,
+                sample_rate,
+                channels,
+            } = audio;
+            // The diarizer needs its own copy; the model consumes the samples
+            let diarizer = diarizer.map(|d| (d, samples.clone()));
+            let levels = LevelEnvelope::new(&samples, sample_rate, channels);
+            let to_segments = |words: &[TimedWord]| -> Vec<Segment> {
+                words
+                    .iter()
+                    .map(|word| Segment {
+                        start: word.start * scale + offset,
+                        end: word.end * scale + offset,
+                        text: word.text.clone(),
+                        confidence: word.confidence,
+                        speaker: None,
+                        level_db: levels.level_db(word.start, word.end),
+                        language: word.language.clone(),
+                    })
+                    .collect()
+            };
+            // Nobody is listening any more (the caller timed out or went
+            // away); stop instead of transcribing the rest of the file
+            let send = |words: &[TimedWord]| match segment_tx.send(to_segments(words)) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(_) => ControlFlow::Break(()),
+            };
+
             let mut guard = futures::executor::block_on(engine.lock());
-            let parakeet = guard.as_mut().ok_or_else(|| anyhow::anyhow!("Model not initialized"))?;
+            let engines = guard
+                .as_mut()
+                .filter(|engines| engines.iter().all(|engine| engine.is_ready()))
+                .ok_or(TranscribeError::ModelNotReady)?;
+            let inference_started = Instant::now();
 
-            parakeet
-                .transcribe_file(&audio_path_for_transcription, Some(TimestampMode::Words))
-                .context("Transcription failed")
-        })
-        .await??;
+            // Only windowed transcription can fail part way with words to
+            // keep; a single pass either succeeds or yields nothing
+            let (text, failure) = match chunking {
+                Some(ref chunking) => {
+                    let windowed = chunking::transcribe_windows(
+                        engines,
+                        samples,
+                        sample_rate,
+                        channels,
+                        chunking,
+                        send,
+                    );
+                    match windowed.failure {
+                        Some(e) if windowed.result.words.is_empty() => return Err(e),
+                        failure => (
+                            windowed.result.text,
+                            failure.map(|e| match e {
+                                TranscribeError::TranscriptionFailed(reason) => reason,
+                                e => e.to_string(),
+                            }),
+                        ),
+                    }
+                }
+                None => {
+                    let result = engines[0].transcribe(samples, sample_rate, channels)?;
+                    let _ = send(&result.words);
+                    (result.text, None)
+                }
+            };
+            drop(guard);
 
-        // Convert tokens to segments with timestamps
-        let segments: Vec<Segment> = result
-            .tokens
-            .iter()
-            .map(|token| Segment {
-                start: token.start as f64,
-                end: token.end as f64,
-                text: token.text.clone(),
-            })
-            .collect();
+            let turns = diarizer
+                .map(|(d, samples)| d.diarize(samples, sample_rate, channels))
+                .transpose()?;
+            let inference_ms = inference_started.elapsed().as_millis() as u64;
+            Ok::<_, TranscribeError>((text, failure, turns, inference_ms))
+        });
+
+        let mut segments: Vec<Segment> = Vec::new();
+        let run = async {
+            while let Some(batch) = segment_rx.recv().await {
+                if streaming {
+                    batch.iter().for_each(&mut on_segment);
+                }
+                segments.extend(batch);
+            }
+            inference.await?
+        };
+
+        // A blocking task can't be cancelled: on timeout the caller gets an
+        // error right away, but inference keeps running and holds the engine
+        // mutex until its current window finishes.
+        let (text, failure, turns, inference_ms) = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, run)
+                .await
+                .map_err(|_| TranscribeError::Timeout(timeout.as_secs()))??,
+            None => run.await?,
+        };
 
-        // Calculate duration from last token or audio file
+        if let Some(ref turns) = turns {
+            diarization::assign_speakers(&mut segments, turns);
+            segments.iter().for_each(&mut on_segment);
+        }
+
+        // Calculate duration from last token or the decoded audio
         let duration = segments
             .last()
-            .map(|s| s.end)
-            .unwrap_or_else(|| get_audio_duration(&audio_path_for_duration).unwrap_or(0.0));
+            .map(|s| s.end - offset)
+            .unwrap_or(audio_duration);
+
+        if let Some(ref failure) = failure {
+            warn!(
+                "Transcription stopped at {:.1}s, returning partial result: {}",
+                duration, failure
+            );
+        }
 
         Ok(TranscriptionResult {
-            text: result.text,
+            text,
             segments,
             duration,
+            language: options.language.clone(),
+            partial: failure.is_some(),
+            warning: failure.map(|e| format!("Transcription failed part way: {}", e)),
+            audio_info,
+            timings: StageTimings {
+                download_ms: 0,
+                convert_ms,
+                inference_ms,
+            },
+        })
+    }
+
+    /// Transcribe raw mono samples, returning word segments timed from the
+    /// start of `samples`. Used for live audio, which never touches disk.
+    pub async fn transcribe_samples(
+        &self,
+        samples: Vec<f32>,
+        sample_rate: u32,
+    ) -> Result<Vec<Segment>> {
+        self.ensure_loaded().await?;
+
+        let engine = self.engine.read().unwrap().clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let mut guard = futures::executor::block_on(engine.lock());
+            let engines = guard
+                .as_mut()
+                .filter(|engines| engines.iter().all(|engine| engine.is_ready()))
+                .ok_or(TranscribeError::ModelNotReady)?;
+
+            engines[0].transcribe(samples, sample_rate, 1)
         })
+        .await??;
+
+        Ok(result
+            .words
+            .into_iter()
+            .map(|word| Segment {
+                start: word.start,
+                end: word.end,
+                text: word.text,
+                confidence: word.confidence,
+                speaker: None,
+                level_db: None,
+                language: word.language,
+            })
+            .collect())
+    }
+}
+
+/// Create `instances` engines to `spec` and load the model into each in a
+/// blocking task (model loading is CPU-intensive), returning them with the
+/// precision that was loaded
+async fn load_engine(
+    spec: EngineSpec,
+    model_path: &Path,
+    instances: usize,
+) -> Result<(Vec<Box<dyn TranscriptionEngine>>, ModelPrecision)> {
+    let model_path = model_path.to_path_buf();
+
+    tokio::task::spawn_blocking(move || {
+        let mut engines = Vec::with_capacity(instances);
+        let mut loaded = spec.precision;
+        for _ in 0..instances {
+            let mut engine = spec.create();
+            loaded = engine.load_model(&model_path)?;
+            engines.push(engine);
+        }
+        Ok((engines, loaded))
+    })
+    .await?
+}
+
+/// Reject empty files, and WAV files whose header can't be read or that
+/// contain no samples
+async fn validate_audio(audio_path: &Path) -> Result<()> {
+    let corrupt = || TranscribeError::InvalidRequest("audio file is empty or corrupt".to_string());
+
+    if tokio::fs::metadata(audio_path).await?.len() == 0 {
+        return Err(corrupt());
+    }
+
+    if is_wav(audio_path)? {
+        let reader = hound::WavReader::open(audio_path).map_err(|_| corrupt())?;
+        if reader.len() == 0 {
+            return Err(corrupt());
+        }
+    }
+
+    Ok(())
+}
+
+/// How much of `duration` seconds of audio a clip covers
+fn clipped_length(clip: Option<Clip>, duration: f64) -> f64 {
+    match clip {
+        Some(clip) => clip.end.map_or(duration, |end| end.min(duration)) - clip.start,
+        None => duration,
+    }
+}
+
+/// Reject clips that start or end beyond the audio, judging by how much
+/// audio ffmpeg decoded for them
+fn check_clip(clip: Clip, decoded_secs: f64) -> Result<()> {
+    if decoded_secs == 0.0 {
+        return Err(TranscribeError::InvalidRequest(format!(
+            "clip_start {} is beyond the end of the audio",
+            clip.start
+        )));
+    }
+
+    if let Some(end) = clip.end {
+        if decoded_secs + CLIP_TOLERANCE_SECS < end - clip.start {
+            return Err(TranscribeError::InvalidRequest(format!(
+                "clip_end {} is beyond the end of the audio ({:.1} seconds)",
+                end,
+                clip.start + decoded_secs
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify ffmpeg can be run, so a missing binary fails startup instead of
+/// every request
+pub fn check_ffmpeg() -> Result<()> {
+    check_tool("ffmpeg")
+}
+
+/// Verify ffprobe can be run. It only speeds up rejecting long files and
+/// serves `/validate`, so unlike ffmpeg it is optional.
+pub fn check_ffprobe() -> Result<()> {
+    check_tool("ffprobe")
+}
+
+fn check_tool(program: &str) -> Result<()> {
+    let output = Command::new(program)
+        .arg("-version")
+        .output()
+        .map_err(|e| spawn_error(program, e))?;
+
+    if !output.status.success() {
+        return Err(TranscribeError::Internal(format!(
+            "{} -version exited with {}",
+            program, output.status
+        )));
     }
+    Ok(())
 }
 
-fn get_audio_duration(path: &Path) -> Result<f64> {
-    let reader = hound::WavReader::open(path)?;
+/// Container format and length of an audio file, as reported by ffprobe
+#[derive(Debug, Clone)]
+pub struct Probe {
+    pub format: String,
+    pub duration: Option<f64>,
+    /// Of the first audio stream
+    pub codec: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+    format: Option<ProbeFormat>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_name: Option<String>,
+    /// ffprobe reports the rate as a string
+    sample_rate: Option<String>,
+    channels: Option<u16>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    format_name: String,
+    duration: Option<String>,
+}
+
+/// Longest ffprobe may take over one file, including reading a remote URL
+const PROBE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest ffprobe waits on a single read from a remote URL
+const PROBE_RW_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Read an audio file's format and duration from its headers with ffprobe,
+/// without decoding it. `input` may be a local path or an HTTP(S) URL, which
+/// ffprobe reads only as far as it needs. ffprobe is killed if it runs past
+/// `PROBE_TIMEOUT` or the caller stops waiting.
+pub async fn probe_audio(input: &str) -> Result<Probe> {
+    let output = tokio::process::Command::new("ffprobe")
+        .args(["-v", "error"])
+        .args(["-rw_timeout", &PROBE_RW_TIMEOUT.as_micros().to_string()])
+        .args(["-select_streams", "a"])
+        .args([
+            "-show_entries",
+            "format=format_name,duration:stream=codec_name,sample_rate,channels",
+        ])
+        .args(["-of", "json"])
+        .arg(input)
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(PROBE_TIMEOUT, output)
+        .await
+        .map_err(|_| TranscribeError::Timeout(PROBE_TIMEOUT.as_secs()))?
+        .map_err(|e| spawn_error("ffprobe", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(TranscribeError::ConversionFailed(format!(
+            "ffprobe exited with {}: {}",
+            output.status,
+            stderr_tail(&stderr)
+        )));
+    }
+
+    let probe: ProbeOutput = serde_json::from_slice(&output.stdout)
+        .map_err(|e| TranscribeError::Internal(format!("Unreadable ffprobe output: {}", e)))?;
+    let format = probe.format.ok_or_else(|| {
+        TranscribeError::ConversionFailed("ffprobe found no container format".to_string())
+    })?;
+    let Some(stream) = probe.streams.into_iter().next() else {
+        return Err(TranscribeError::ConversionFailed(
+            "file has no audio stream".to_string(),
+        ));
+    };
+
+    Ok(Probe {
+        format: format.format_name,
+        duration: format.duration.and_then(|d| d.parse().ok()),
+        codec: stream.codec_name,
+        sample_rate: stream.sample_rate.and_then(|rate| rate.parse().ok()),
+        channels: stream.channels,
+    })
+}
+
+/// Properties of an audio file, from its headers: ffprobe reads any format
+/// ffmpeg does, and the WAV header is read when ffprobe is missing or fails.
+/// `None` when neither can tell.
+pub async fn probe_audio_info(path: &Path) -> Option<AudioInfo> {
+    match probe_audio(&path.to_string_lossy()).await {
+        Ok(probe) => Some(AudioInfo {
+            sample_rate: probe.sample_rate,
+            channels: probe.channels,
+            codec: probe.codec,
+            original_duration: probe.duration,
+        }),
+        Err(_) => wav_info(path),
+    }
+}
+
+fn wav_info(path: &Path) -> Option<AudioInfo> {
+    let reader = hound::WavReader::open(path).ok()?;
     let spec = reader.spec();
-    let num_samples = reader.len() as f64;
-    let sample_rate = spec.sample_rate as f64;
-    let channels = spec.channels as f64;
-    Ok(num_samples / sample_rate / channels)
+    Some(AudioInfo {
+        sample_rate: Some(spec.sample_rate),
+        channels: Some(spec.channels),
+        codec: Some(wav_codec(spec)),
+        original_duration: Some(reader.duration() as f64 / spec.sample_rate as f64),
+    })
+}
+
+/// A WAV file's sample encoding, named the way ffprobe names it
+fn wav_codec(spec: hound::WavSpec) -> String {
+    match spec.sample_format {
+        hound::SampleFormat::Float => format!("pcm_f{}le", spec.bits_per_sample),
+        hound::SampleFormat::Int if spec.bits_per_sample == 8 => "pcm_u8".to_string(),
+        hound::SampleFormat::Int => format!("pcm_s{}le", spec.bits_per_sample),
+    }
+}
+
+/// Failing to start ffmpeg or ffprobe is a deployment problem, not a problem
+/// with the audio
+fn spawn_error(program: &str, e: std::io::Error) -> TranscribeError {
+    if e.kind() == std::io::ErrorKind::NotFound {
+        TranscribeError::Internal(format!("{} is not installed or not on PATH", program))
+    } else {
+        TranscribeError::Internal(format!("Failed to run {}: {}", program, e))
+    }
+}
+
+/// The last few lines of ffmpeg's stderr, where the actual error is reported
+fn stderr_tail(stderr: &str) -> String {
+    const TAIL_LINES: usize = 5;
+
+    let lines: Vec<&str> = stderr
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    lines[lines.len().saturating_sub(TAIL_LINES)..].join("\n")
+}
+
+/// A value from loudnorm's summary, e.g. `-27.5 LUFS` for `Input Integrated:`
+fn loudnorm_value<'a>(stderr: &'a str, label: &str) -> Option<&'a str> {
+    stderr
+        .lines()
+        .find_map(|line| line.trim().strip_prefix(label))
+        .map(str::trim)
+}
+
+/// Whether the file starts with a RIFF/WAVE header, whatever its extension
+fn is_wav(path: &Path) -> Result<bool> {
+    let mut header = [0u8; 12];
+    let mut file = std::fs::File::open(path)?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(&header[0..4] == b"RIFF" && &header[8..12] == b"WAVE"),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Read a WAV file as interleaved f32 samples with its sample rate and channel count
+pub(crate) fn read_wav_samples(path: &Path) -> Result<(Vec<f32>, u32, u16)> {
+    let reader = hound::WavReader::open(path)
+        .map_err(|e| TranscribeError::Unsupported(format!("Invalid WAV file: {}", e)))?;
+    wav_samples(reader)
+}
+
+/// Samples of an open WAV file, scaled to `[-1, 1]`
+fn wav_samples<R: Read>(reader: hound::WavReader<R>) -> Result<(Vec<f32>, u32, u16)> {
+    let spec = reader.spec();
+
+    let samples: std::result::Result<Vec<f32>, hound::Error> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect(),
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect()
+        }
+    };
+    let samples =
+        samples.map_err(|e| TranscribeError::Unsupported(format!("Invalid WAV file: {}", e)))?;
+
+    Ok((samples, spec.sample_rate, spec.channels))
 }
 
 impl Default for Transcriber {
     fn default() -> Self {
-        Self::new()
+        Self::new(
+            AudioFormat::default(),
+            None,
+            ProfileChunking::default(),
+            None,
+            1,
+            EngineSpec {
+                backend: Backend::default(),
+                precision: ModelPrecision::default(),
+                temp_dir: std::env::temp_dir(),
+            },
+        )
     }
 }